@@ -11,6 +11,7 @@ const libs = (
       'serde-wasm-bindgen',
       'serde-json',
       'serde-wasm-bindgen-reftypes',
+      'serde-wasm-bindgen-batched',
       'msgpack'
     ].map(async dir => {
       try {
@@ -66,9 +67,21 @@ async function loadData(name) {
   return JSON.parse(await readFile(`./data/${name}.json`, 'utf8'));
 }
 
+// The other datasets are nested at most 10 levels deep. This one has 100 chains of 40 objects,
+// i.e. 80 levels of objects and arrays, to measure the cost of the deeper levels, where
+// the deserializer checks containers for cycles.
+function nestedData(chains = 100, length = 40) {
+  const chain = value => ({
+    value,
+    children: value > 1 ? [chain(value - 1)] : []
+  });
+  return { value: 0, children: Array.from({ length: chains }, () => chain(length)) };
+}
+
 const datasets = {
   Canada: await loadData('canada'),
   CitmCatalog: await loadData('citm_catalog'),
+  Nested: nestedData(),
   Twitter: await loadData('twitter')
 };
 
@@ -36,7 +36,7 @@ macro_rules! serde_impl {
 }
 
 serde_impl!(|input| {
-    "serde-wasm-bindgen" => {
+    "serde-wasm-bindgen" => #[cfg(not(feature = "serde-wasm-bindgen-batched"))] {
         parse: serde_wasm_bindgen::from_value(input).unwrap_throw(),
         serialize: {
             const SERIALIZER: serde_wasm_bindgen::Serializer = serde_wasm_bindgen::Serializer::json_compatible();
@@ -44,6 +44,14 @@ serde_impl!(|input| {
         },
     },
 
+    "serde-wasm-bindgen-batched" => {
+        parse: serde_wasm_bindgen::from_value_batched(input, &serde_wasm_bindgen::DeserializerConfig::new()).unwrap_throw(),
+        serialize: {
+            const SERIALIZER: serde_wasm_bindgen::Serializer = serde_wasm_bindgen::Serializer::json_compatible();
+            SERIALIZER.serialize_batched(input).unwrap_throw()
+        },
+    },
+
     "serde-json" => #[allow(deprecated)] {
         parse: input.into_serde().unwrap_throw(),
         serialize: JsValue::from_serde(input).unwrap_throw(),
@@ -100,5 +108,6 @@ macro_rules! datasets {
 datasets! {
     canada::Canada,
     citm_catalog::CitmCatalog,
+    nested::Nested,
     twitter::Twitter,
 }
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// A synthetic tree that is nested deeper than the other datasets, past the depth
+/// from which the deserializer checks containers for cycles.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Nested {
+    pub value: u32,
+    pub children: Vec<Nested>,
+}
@@ -1,18 +1,52 @@
-use js_sys::{Array, ArrayBuffer, JsString, Number, Object, Symbol, Uint8Array};
-use serde::de::value::{MapDeserializer, SeqDeserializer};
+use js_sys::{Array, ArrayBuffer, Boolean, JsString, Number, Object, Symbol, Uint8Array};
 use serde::de::{self, IntoDeserializer};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::ops::Deref;
+use std::rc::Rc;
 use wasm_bindgen::{JsCast, JsValue, UnwrapThrowExt};
 
-use super::{static_str_to_js, Error, ObjectExt, Result};
-use crate::{JsValueKeeper, NEXT_PRESERVE};
+use super::{
+    inherits_object_prototype, object_prototype_has, static_str_to_js, EnumRepresentation, Error,
+    ErrorKind, NumberPolicy, ObjectExt, PathSegment, Result,
+};
+use crate::preserve::{self, PRESERVE_NAME};
+use crate::typed_array::TypedArrayBuffer;
+
+mod in_place;
+mod merge;
+#[cfg(feature = "batch")]
+mod snapshot;
+pub(crate) use in_place::deserialize_in_place;
+pub(crate) use merge::merge;
+pub use merge::{Merge, Patch};
+#[cfg(feature = "batch")]
+pub(crate) use snapshot::deserialize_batched;
+
+/// Provides [`de::SeqAccess`] from any JS iterator or array.
+struct SeqAccess<I> {
+    iter: I,
+    idx: u32,
+    config: Rc<Context>,
+}
 
-/// Provides [`de::SeqAccess`] from any JS iterator.
-struct SeqAccess {
-    iter: js_sys::IntoIter,
+impl<I> SeqAccess<I> {
+    const fn new(iter: I, config: Rc<Context>) -> Self {
+        Self {
+            iter,
+            idx: 0,
+            config,
+        }
+    }
 }
 
-impl<'de> de::SeqAccess<'de> for SeqAccess {
+impl<'de, I, E> de::SeqAccess<'de> for SeqAccess<I>
+where
+    I: Iterator<Item = std::result::Result<JsValue, E>>,
+    Error: From<E>,
+{
     type Error = Error;
 
     fn next_element_seed<T: de::DeserializeSeed<'de>>(
@@ -20,28 +54,74 @@ impl<'de> de::SeqAccess<'de> for SeqAccess {
         seed: T,
     ) -> Result<Option<T::Value>> {
         Ok(match self.iter.next().transpose()? {
-            Some(value) => Some(seed.deserialize(Deserializer::from(value))?),
+            Some(value) => {
+                let idx = self.idx;
+                check_limit(self.config.max_sequence_length, idx + 1, "sequence length")?;
+                self.config.count_nodes(1)?;
+                self.idx += 1;
+                let value = Deserializer::new(value, &self.config);
+                Some(
+                    self.config
+                        .path
+                        .nested(|| PathSegment::Index(idx), || seed.deserialize(value))?,
+                )
+            }
             None => None,
         })
     }
 }
 
-/// Provides [`serde::de::MapAccess`] from any JS iterator that returns `[key, value]` pairs.
-struct MapAccess {
-    iter: js_sys::IntoIter,
+/// Provides [`serde::de::MapAccess`] from any JS iterator or array of `[key, value]` pairs.
+struct MapAccess<I> {
+    iter: I,
+    idx: u32,
+    /// Key of the current entry, used to report errors in its value.
+    next_key: JsValue,
     next_value: Option<Deserializer>,
+    /// Whether the keys are property names of an object, which are always strings.
+    property_keys: bool,
+    config: Rc<Context>,
 }
 
-impl MapAccess {
-    const fn new(iter: js_sys::IntoIter) -> Self {
+impl<I> MapAccess<I> {
+    const fn new(iter: I, config: Rc<Context>) -> Self {
         Self {
             iter,
+            idx: 0,
+            next_key: JsValue::UNDEFINED,
             next_value: None,
+            property_keys: false,
+            config,
+        }
+    }
+
+    /// Creates a [`MapAccess`] for the entries of an object, whose keys are parsed into
+    /// numbers, bools or chars as needed.
+    fn for_object(iter: I, config: Rc<Context>) -> Self {
+        Self {
+            property_keys: true,
+            ..Self::new(iter, config)
+        }
+    }
+
+    fn deserialize_key<'de, K: de::DeserializeSeed<'de>>(
+        &self,
+        key: Deserializer,
+        seed: K,
+    ) -> Result<K::Value> {
+        if !self.property_keys {
+            return seed.deserialize(key);
         }
+        key.check_string_length()?;
+        seed.deserialize(PropertyKey(&key.value.as_string().unwrap_throw()))
     }
 }
 
-impl<'de> de::MapAccess<'de> for MapAccess {
+impl<'de, I, E> de::MapAccess<'de> for MapAccess<I>
+where
+    I: Iterator<Item = std::result::Result<JsValue, E>>,
+    Error: From<E>,
+{
     type Error = Error;
 
     fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
@@ -49,32 +129,93 @@ impl<'de> de::MapAccess<'de> for MapAccess {
 
         Ok(match self.iter.next().transpose()? {
             Some(pair) => {
-                let (key, value) = convert_pair(pair);
+                check_limit(
+                    self.config.max_map_entries,
+                    self.idx + 1,
+                    "number of map entries",
+                )?;
+                self.config.count_nodes(2)?;
+                let (key, value) = convert_pair(pair, &self.config);
+                self.idx += 1;
+                self.next_key = key.value.clone();
                 self.next_value = Some(value);
-                Some(seed.deserialize(key)?)
+                let idx = self.idx - 1;
+                Some(self.config.path.nested(
+                    || PathSegment::MapKey(idx),
+                    || self.deserialize_key(key, seed),
+                )?)
             }
             None => None,
         })
     }
 
     fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
-        seed.deserialize(self.next_value.take().unwrap_throw())
+        self.next_value_with(|value| seed.deserialize(value))
+    }
+}
+
+impl<I> MapAccess<I> {
+    /// Passes the value of the current entry to `f`, adding its key to the path of errors.
+    fn next_value_with<T>(&mut self, f: impl FnOnce(Deserializer) -> Result<T>) -> Result<T> {
+        let value = self.next_value.take().unwrap_throw();
+        let (key, idx) = (&self.next_key, self.idx - 1);
+        self.config.path.nested(
+            || match key.as_string() {
+                Some(key) => PathSegment::Field(key),
+                None => PathSegment::MapValue(idx),
+            },
+            || f(value),
+        )
     }
 }
 
 struct ObjectAccess {
     obj: ObjectExt,
+    /// Whether the object inherits directly from `Object.prototype`.
+    plain: bool,
+    all_fields: &'static [&'static str],
     fields: std::slice::Iter<'static, &'static str>,
+    /// Own enumerable keys of the object and the index of the next one to check,
+    /// collected once all known fields are processed (unless unknown fields are ignored).
+    unknown_keys: Option<(Array, u32)>,
+    /// A property that is neither a field nor unknown, such as the tag of an internally tagged enum.
+    ignored_key: Option<&'static str>,
+    /// Key of the current property, used to report errors in its value.
+    next_key: Cow<'static, str>,
     next_value: Option<Deserializer>,
+    config: Rc<Context>,
 }
 
 impl ObjectAccess {
-    fn new(obj: ObjectExt, fields: &'static [&'static str]) -> Self {
+    fn new(obj: ObjectExt, fields: &'static [&'static str], config: Rc<Context>) -> Self {
         Self {
+            plain: inherits_object_prototype(&obj),
             obj,
+            all_fields: fields,
             fields: fields.iter(),
+            unknown_keys: None,
+            ignored_key: None,
+            next_key: Cow::Borrowed(""),
             next_value: None,
+            config,
+        }
+    }
+
+    /// Returns the next own enumerable key of the object that doesn't correspond to any struct field.
+    fn next_unknown_key(&mut self) -> Option<(JsString, String)> {
+        let obj = &self.obj;
+        let (keys, idx) = self
+            .unknown_keys
+            .get_or_insert_with(|| (Object::keys(obj.unchecked_ref::<Object>()), 0));
+        while *idx < keys.length() {
+            let js_key = keys.get(*idx).unchecked_into::<JsString>();
+            *idx += 1;
+            let key = String::from(&js_key);
+            if !self.all_fields.contains(&key.as_str()) && self.ignored_key != Some(key.as_str()) {
+                return Some((js_key, key));
+            }
         }
+        None
     }
 }
 
@@ -82,41 +223,150 @@ fn str_deserializer(s: &str) -> de::value::StrDeserializer<Error> {
     de::IntoDeserializer::into_deserializer(s)
 }
 
+/// Deserializes a property name used as a map key.
+///
+/// Property names are always strings, so like `serde_json` they are parsed for integer,
+/// float, bool and char keys, e.g. `{ "1": "a" }` into a `HashMap<u32, String>`.
+struct PropertyKey<'a>(&'a str);
+
+macro_rules! deserialize_parsed_key {
+    ($($name:ident => $visit:ident,)*) => {
+        $(fn $name<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            match self.0.parse() {
+                Ok(v) => visitor.$visit(v),
+                Err(_) => Err(de::Error::invalid_type(de::Unexpected::Str(self.0), &visitor)),
+            }
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for PropertyKey<'_> {
+    type Error = Error;
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_str(self.0)
+    }
+
+    /// A property always exists, so keys of type `Option<K>` are never `None`.
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_enum(str_deserializer(self.0), name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
 impl<'de> de::MapAccess<'de> for ObjectAccess {
     type Error = Error;
 
     fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
         debug_assert!(self.next_value.is_none());
 
+        if !self.next_property()? {
+            return Ok(None);
+        }
+        Ok(Some(seed.deserialize(str_deserializer(&self.next_key))?))
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        self.next_value_with(|value| seed.deserialize(value))
+    }
+}
+
+impl ObjectAccess {
+    /// Moves on to the next field present in the object, or the next unknown property
+    /// if these are passed on, and returns whether there is one.
+    fn next_property(&mut self) -> Result<bool> {
         for field in &mut self.fields {
-            let js_field = static_str_to_js(field);
-            let next_value = self.obj.get_with_ref_key(&js_field);
-            // If this value is `undefined`, it might be actually a missing field;
-            // double-check with an `in` operator if so.
-            let is_missing_field = next_value.is_undefined() && !js_field.js_in(&self.obj);
-            if !is_missing_field {
-                self.next_value = Some(Deserializer::from(next_value));
-                return Ok(Some(seed.deserialize(str_deserializer(field))?));
+            let may_be_inherited = self.config.may_be_inherited(self.plain, field);
+            if let Some(next_value) = self
+                .obj
+                .get_field(&static_str_to_js(field), may_be_inherited)
+            {
+                self.config.count_nodes(1)?;
+                self.next_key = Cow::Borrowed(field);
+                self.next_value = Some(Deserializer::new(next_value, &self.config));
+                return Ok(true);
             }
         }
 
-        Ok(None)
+        match &self.config.unknown_fields {
+            UnknownFields::Ignore => {}
+            UnknownFields::Deny => {
+                if let Some((js_key, key)) = self.next_unknown_key() {
+                    self.config.count_nodes(1)?;
+                    let next_value = self.obj.get_with_ref_key(&js_key);
+                    self.next_value = Some(Deserializer::new(next_value, &self.config));
+                    self.next_key = Cow::Owned(key);
+                    return Ok(true);
+                }
+            }
+            UnknownFields::Warn(callback) => {
+                let callback = Rc::clone(callback);
+                while let Some((_, key)) = self.next_unknown_key() {
+                    self.config.path.report(&*callback, key);
+                }
+            }
+        }
+
+        Ok(false)
     }
 
-    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
-        seed.deserialize(self.next_value.take().unwrap_throw())
+    /// Passes the value of the current property to `f`, adding its key to the path of errors.
+    fn next_value_with<T>(&mut self, f: impl FnOnce(Deserializer) -> Result<T>) -> Result<T> {
+        let value = self.next_value.take().unwrap_throw();
+        let key = &self.next_key;
+        self.config
+            .path
+            .nested(|| PathSegment::Field(key.to_string()), || f(value))
     }
 }
 
 /// Provides [`serde::de::EnumAccess`] from given JS values for the `tag` and the `payload`.
 struct EnumAccess {
     tag: Deserializer,
-    payload: Deserializer,
+    payload: VariantAccess,
 }
 
 impl<'de> de::EnumAccess<'de> for EnumAccess {
     type Error = Error;
-    type Variant = Deserializer;
+    type Variant = VariantAccess;
 
     fn variant_seed<V: de::DeserializeSeed<'de>>(
         self,
@@ -126,14 +376,487 @@ impl<'de> de::EnumAccess<'de> for EnumAccess {
     }
 }
 
+/// Provides [`serde::de::VariantAccess`] for the payload of an enum variant.
+struct VariantAccess {
+    payload: Deserializer,
+    /// Name of the tag property if the payload is the internally tagged object itself.
+    internal_tag: Option<&'static str>,
+    /// Key of the property holding the payload, used to report errors in it.
+    payload_key: Option<JsValue>,
+}
+
+impl VariantAccess {
+    const fn new(payload: Deserializer) -> Self {
+        Self {
+            payload,
+            internal_tag: None,
+            payload_key: None,
+        }
+    }
+
+    /// Deserializes the payload, adding the key of the property holding it to the path.
+    fn with_path<T>(self, f: impl FnOnce(Deserializer) -> Result<T>) -> Result<T> {
+        let VariantAccess {
+            payload,
+            payload_key,
+            ..
+        } = self;
+        match payload_key {
+            Some(key) => {
+                let config = Rc::clone(&payload.config);
+                config.path.nested(
+                    || PathSegment::Field(key.as_string().unwrap_or_default()),
+                    || f(payload),
+                )
+            }
+            None => f(payload),
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.internal_tag {
+            // Any other properties of the tagged object are ignored.
+            Some(_) => Ok(()),
+            None => self.with_path(de::VariantAccess::unit_variant),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        match self.internal_tag {
+            Some(tag) => {
+                // Make a copy without the tag so that it's not visible to maps and strict structs,
+                // leaving the input untouched even if it's frozen or used elsewhere.
+                let Deserializer { value, config } = self.payload;
+                let copy = Object::create(&Object::get_prototype_of(&value));
+                copy.unchecked_ref::<ObjectExt>()
+                    .assign_own(value.unchecked_ref());
+                js_sys::Reflect::delete_property(&copy, &static_str_to_js(tag))?;
+                seed.deserialize(Deserializer {
+                    value: copy.into(),
+                    config,
+                })
+            }
+            None => {
+                self.with_path(|payload| de::VariantAccess::newtype_variant_seed(payload, seed))
+            }
+        }
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        match self.internal_tag {
+            Some(_) => Err(de::Error::custom(
+                "tuple variants can't be represented with an internal tag",
+            )),
+            None => {
+                self.with_path(|payload| de::VariantAccess::tuple_variant(payload, len, visitor))
+            }
+        }
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.internal_tag {
+            Some(tag) => {
+                let Deserializer { value, config } = self.payload;
+                let mut access = ObjectAccess::new(value.unchecked_into(), fields, config);
+                access.ignored_key = Some(tag);
+                visitor.visit_map(access)
+            }
+            None => self
+                .with_path(|payload| de::VariantAccess::struct_variant(payload, fields, visitor)),
+        }
+    }
+}
+
+/// Controls what happens to properties of a JS object that don't correspond
+/// to any field of the Rust struct it's deserialized into.
+#[derive(Clone)]
+pub enum UnknownFields {
+    /// Unknown properties are not even looked at. This is the default.
+    Ignore,
+    /// Unknown properties are passed to the struct's visitor, so that structs with
+    /// `#[serde(deny_unknown_fields)]` reject them.
+    Deny,
+    /// Unknown properties are reported to the given callback without failing the
+    /// deserialization. The callback receives the full path of each property,
+    /// ending with a [`PathSegment::Field`] holding its name.
+    Warn(UnknownFieldCallback),
+}
+
+/// Callback that receives the path of an unknown property, see [`UnknownFields::Warn`].
+pub type UnknownFieldCallback = Rc<dyn Fn(&[PathSegment])>;
+
+impl std::fmt::Debug for UnknownFields {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UnknownFields::Ignore => f.write_str("Ignore"),
+            UnknownFields::Deny => f.write_str("Deny"),
+            UnknownFields::Warn(_) => f.write_str("Warn(..)"),
+        }
+    }
+}
+
+/// Options that control how a [`Deserializer`] converts JavaScript values into Rust types.
+///
+/// The same configuration is applied to every nested value, so the options chosen
+/// for the top-level [`Deserializer`] hold for the whole input.
+#[derive(Clone, Debug)]
+pub struct DeserializerConfig {
+    deserialize_null_as_missing: bool,
+    deserialize_bytes_from_arrays: bool,
+    json_values_only: bool,
+    unknown_fields: UnknownFields,
+    enum_representation: EnumRepresentation,
+    number_policy: Option<NumberPolicy>,
+    coerce_primitives: bool,
+    max_depth: Option<u32>,
+    max_nodes: Option<u32>,
+    max_sequence_length: Option<u32>,
+    max_string_length: Option<u32>,
+    max_bytes_length: Option<u32>,
+    max_map_entries: Option<u32>,
+}
+
+impl Default for DeserializerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeserializerConfig {
+    /// Creates a new default [`DeserializerConfig`].
+    pub const fn new() -> Self {
+        Self {
+            deserialize_null_as_missing: true,
+            deserialize_bytes_from_arrays: true,
+            json_values_only: false,
+            unknown_fields: UnknownFields::Ignore,
+            enum_representation: EnumRepresentation::External,
+            number_policy: None,
+            coerce_primitives: false,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_nodes: None,
+            max_sequence_length: None,
+            max_string_length: None,
+            max_bytes_length: None,
+            max_map_entries: None,
+        }
+    }
+
+    /// Creates a configuration that only accepts what `JSON.parse` can produce, the same way
+    /// `serde_json` would read the equivalent JSON text.
+    ///
+    /// Both `null` and `undefined` are treated as missing values, bytes are read from arrays
+    /// and maps from objects, as produced by
+    /// [`Serializer::json_compatible`](crate::Serializer::json_compatible), while `bigint`s,
+    /// `Map`s, `Set`s, typed arrays and other non-JSON values are rejected.
+    pub fn json_compatible() -> Self {
+        Self {
+            json_values_only: true,
+            ..Self::new()
+        }
+    }
+
+    /// Creates a strict configuration that only accepts the canonical
+    /// representations produced by the default [`Serializer`](crate::Serializer).
+    pub fn strict() -> Self {
+        Self {
+            deserialize_null_as_missing: false,
+            deserialize_bytes_from_arrays: false,
+            unknown_fields: UnknownFields::Deny,
+            ..Self::new()
+        }
+    }
+
+    /// Set to `false` to only accept `undefined` for `()`, unit structs and `Option::None`
+    /// instead of both `null` and `undefined`. `true` by default.
+    pub const fn deserialize_null_as_missing(mut self, value: bool) -> Self {
+        self.deserialize_null_as_missing = value;
+        self
+    }
+
+    /// Set to `false` to only accept `Uint8Array` and `ArrayBuffer` for bytes
+    /// instead of also accepting plain JavaScript arrays. `true` by default.
+    pub const fn deserialize_bytes_from_arrays(mut self, value: bool) -> Self {
+        self.deserialize_bytes_from_arrays = value;
+        self
+    }
+
+    /// Set to `true` to only accept values that `JSON.parse` can produce, i.e. to reject
+    /// `bigint`s, `Map`s, `Set`s and other iterables except arrays, typed arrays and
+    /// `ArrayBuffer`s. `false` by default.
+    pub const fn json_values_only(mut self, value: bool) -> Self {
+        self.json_values_only = value;
+        self
+    }
+
+    /// Sets how own enumerable properties of JS objects that don't correspond to any
+    /// struct field are handled. [`UnknownFields::Ignore`] by default.
+    pub fn unknown_fields(mut self, value: UnknownFields) -> Self {
+        self.unknown_fields = value;
+        self
+    }
+
+    /// Sets the expected representation of enum variants. [`EnumRepresentation::External`] by default.
+    pub const fn enum_representation(mut self, value: EnumRepresentation) -> Self {
+        self.enum_representation = value;
+        self
+    }
+
+    /// Sets the expected representation of `u64`, `i64`, `usize`, `isize`, `u128` and `i128`.
+    ///
+    /// When set, any of these types can be deserialized both from safe integer `number`s
+    /// and from `bigint`s, as well as from decimal `string`s with [`NumberPolicy::String`].
+    /// When not set, 64-bit numbers are accepted as `number`s or `bigint`s and
+    /// 128-bit numbers only as `bigint`s.
+    pub const fn number_policy(mut self, value: NumberPolicy) -> Self {
+        self.number_policy = Some(value);
+        self
+    }
+
+    /// Set to `true` to coerce JS values of a different type into Rust primitives where
+    /// this can be done without loss. `false` by default.
+    ///
+    /// This accepts boxed `Number`, `String` and `Boolean` objects, numeric strings for
+    /// integers and floats, `bigint`s for all integers and safe integer `number`s for
+    /// `u128` and `i128`. Values that don't fit into the target type are still rejected.
+    pub const fn coerce_primitives(mut self, value: bool) -> Self {
+        self.coerce_primitives = value;
+        self
+    }
+
+    /// Sets the maximum number of nested arrays, objects and other containers,
+    /// or `None` to allow any nesting. `Some(128)` by default.
+    ///
+    /// Deeper input is rejected with [`ErrorKind::DepthLimit`] instead of overflowing the stack.
+    /// Objects that contain themselves are rejected with [`ErrorKind::Cycle`] once they
+    /// are nested 32 levels deep, or with [`ErrorKind::DepthLimit`] if the limit is lower.
+    pub const fn max_depth(mut self, value: Option<u32>) -> Self {
+        self.max_depth = value;
+        self
+    }
+
+    /// Sets the maximum total number of sequence elements, map keys and values and
+    /// struct fields in the input. Unlimited by default.
+    pub const fn max_nodes(mut self, value: Option<u32>) -> Self {
+        self.max_nodes = value;
+        self
+    }
+
+    /// Sets the maximum length of arrays, typed arrays and other iterables deserialized
+    /// as sequences. Unlimited by default.
+    pub const fn max_sequence_length(mut self, value: Option<u32>) -> Self {
+        self.max_sequence_length = value;
+        self
+    }
+
+    /// Sets the maximum length of strings in UTF-16 code units, as reported by their
+    /// `length` property. Unlimited by default.
+    pub const fn max_string_length(mut self, value: Option<u32>) -> Self {
+        self.max_string_length = value;
+        self
+    }
+
+    /// Sets the maximum length of `Uint8Array`s, `ArrayBuffer`s and arrays deserialized
+    /// as bytes. Unlimited by default.
+    pub const fn max_bytes_length(mut self, value: Option<u32>) -> Self {
+        self.max_bytes_length = value;
+        self
+    }
+
+    /// Sets the maximum number of entries of `Map`s, other iterables of pairs and objects
+    /// deserialized as maps. Unlimited by default.
+    pub const fn max_map_entries(mut self, value: Option<u32>) -> Self {
+        self.max_map_entries = value;
+        self
+    }
+}
+
+const DEFAULT_MAX_DEPTH: u32 = 128;
+
+/// Depth from which containers are checked for cycles.
+///
+/// An object that contains itself always leads to deep nesting, so there is no need to pay
+/// for tracking identities of containers at the depths that almost all input stays within.
+const CYCLE_CHECK_DEPTH: u32 = 32;
+
+fn depth_limit_error(max_depth: u32) -> Error {
+    Error::with_kind(
+        ErrorKind::DepthLimit,
+        format_args!("nesting depth exceeds the limit of {}", max_depth),
+    )
+}
+
+/// Fails with [`ErrorKind::LimitExceeded`] if `len` is larger than the limit.
+fn check_limit(limit: Option<u32>, len: u32, what: &str) -> Result<()> {
+    match limit {
+        Some(max) if len > max => Err(Error::with_kind(
+            ErrorKind::LimitExceeded,
+            format_args!("{} exceeds the limit of {}", what, max),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks whether a string has the syntax of an integer, so that failing to parse it
+/// means that the integer doesn't fit into the target type.
+fn is_integer_string(s: &str) -> bool {
+    let digits = s.strip_prefix(|c| c == '-' || c == '+').unwrap_or(s);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn cycle_error() -> Error {
+    Error::with_kind(
+        ErrorKind::Cycle,
+        "object contains itself and can't be deserialized",
+    )
+}
+
+/// The configuration shared by all nested [`Deserializer`]s, together with the state of the traversal.
+struct Context {
+    config: DeserializerConfig,
+    depth: Cell<u32>,
+    nodes: Cell<u32>,
+    /// Containers deeper than [`CYCLE_CHECK_DEPTH`] that are currently being deserialized,
+    /// created on first use.
+    visiting: RefCell<Option<js_sys::Set>>,
+    path: CurrentPath,
+    /// Whether the value is deserialized into an existing one, whose allocations should be reused.
+    in_place: bool,
+    /// Field names already checked against `Object.prototype`, see [`Context::may_be_inherited`].
+    prototype_keys: RefCell<HashMap<&'static str, bool>>,
+}
+
+impl Context {
+    fn new(config: DeserializerConfig) -> Rc<Self> {
+        Rc::new(Self {
+            path: CurrentPath::new(&config),
+            config,
+            depth: Cell::new(0),
+            nodes: Cell::new(0),
+            visiting: RefCell::new(None),
+            in_place: false,
+            prototype_keys: RefCell::default(),
+        })
+    }
+
+    fn in_place(config: DeserializerConfig) -> Rc<Self> {
+        Rc::new(Self {
+            path: CurrentPath::new(&config),
+            config,
+            depth: Cell::new(0),
+            nodes: Cell::new(0),
+            visiting: RefCell::new(None),
+            in_place: true,
+            prototype_keys: RefCell::default(),
+        })
+    }
+
+    /// Checks whether a property might be inherited rather than own: always for objects with
+    /// a custom prototype, and for plain objects only if `Object.prototype` provides the key,
+    /// which is checked once per key and deserialization.
+    fn may_be_inherited(&self, plain: bool, key: &'static str) -> bool {
+        !plain
+            || *self
+                .prototype_keys
+                .borrow_mut()
+                .entry(key)
+                .or_insert_with(|| object_prototype_has(&static_str_to_js(key)))
+    }
+
+    /// Counts values that are about to be deserialized towards [`DeserializerConfig::max_nodes`].
+    fn count_nodes(&self, n: u32) -> Result<()> {
+        let nodes = self.nodes.get().saturating_add(n);
+        check_limit(self.max_nodes, nodes, "number of values")?;
+        self.nodes.set(nodes);
+        Ok(())
+    }
+}
+
+impl Deref for Context {
+    type Target = DeserializerConfig;
+
+    fn deref(&self) -> &DeserializerConfig {
+        &self.config
+    }
+}
+
+/// The path from the root to the value being deserialized, only tracked
+/// if unknown fields are reported to a callback.
+struct CurrentPath(Option<RefCell<Vec<PathSegment>>>);
+
+impl CurrentPath {
+    fn new(config: &DeserializerConfig) -> Self {
+        match config.unknown_fields {
+            UnknownFields::Warn(_) => CurrentPath(Some(RefCell::default())),
+            _ => CurrentPath(None),
+        }
+    }
+
+    /// Deserializes a nested value, adding the given segment to the path of its errors.
+    fn nested<T>(
+        &self,
+        segment: impl Fn() -> PathSegment,
+        f: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let result = match &self.0 {
+            Some(path) => {
+                path.borrow_mut().push(segment());
+                let result = f();
+                path.borrow_mut().pop();
+                result
+            }
+            None => f(),
+        };
+        result.map_err(|err| err.at(segment()))
+    }
+
+    /// Passes the full path of an unknown property to the callback.
+    fn report(&self, callback: &dyn Fn(&[PathSegment]), key: String) {
+        let mut path = self
+            .0
+            .as_ref()
+            .map_or_else(Vec::new, |path| path.borrow().clone());
+        path.push(PathSegment::Field(key));
+        callback(&path);
+    }
+}
+
+/// Marks a container as being deserialized until dropped.
+struct Visit {
+    cx: Rc<Context>,
+    /// The container if it's checked for cycles.
+    value: Option<JsValue>,
+}
+
+impl Drop for Visit {
+    fn drop(&mut self) {
+        self.cx.depth.set(self.cx.depth.get() - 1);
+        if let (Some(visiting), Some(value)) = (&*self.cx.visiting.borrow(), &self.value) {
+            visiting.delete(value);
+        }
+    }
+}
+
 /// A newtype that allows using any [`JsValue`] as a [`serde::Deserializer`].
 pub struct Deserializer {
     value: JsValue,
+    config: Rc<Context>,
 }
 
 impl From<JsValue> for Deserializer {
     fn from(value: JsValue) -> Self {
-        Self { value }
+        Self {
+            value,
+            config: Context::new(DeserializerConfig::new()),
+        }
     }
 }
 
@@ -147,13 +870,67 @@ impl<'de> IntoDeserializer<'de, Error> for Deserializer {
     }
 }
 
+/// Entries of a JS value deserialized as a map, see [`Deserializer::entries`].
+enum Entries {
+    /// An iterable expected to return `[key, value]` pairs, such as a `Map`.
+    Iter(js_sys::IntoIter),
+    /// The result of `Object.entries`, whose keys are property names.
+    Object(Array),
+}
+
 /// Destructures a JS `[key, value]` pair into a tuple of [`Deserializer`]s.
-fn convert_pair(pair: JsValue) -> (Deserializer, Deserializer) {
+fn convert_pair(pair: JsValue, config: &Rc<Context>) -> (Deserializer, Deserializer) {
     let pair = pair.unchecked_into::<Array>();
-    (pair.get(0).into(), pair.get(1).into())
+    (
+        Deserializer::new(pair.get(0), config),
+        Deserializer::new(pair.get(1), config),
+    )
 }
 
 impl Deserializer {
+    /// Creates a [`Deserializer`] for the given value that uses the given configuration.
+    pub fn with_config(value: JsValue, config: &DeserializerConfig) -> Self {
+        Self {
+            value,
+            config: Context::new(config.clone()),
+        }
+    }
+
+    /// Creates a nested [`Deserializer`] that shares the configuration of its parent.
+    fn new(value: JsValue, config: &Rc<Context>) -> Self {
+        Self {
+            value,
+            config: Rc::clone(config),
+        }
+    }
+
+    /// Starts deserializing the contents of a container, checking for cycles and the depth limit.
+    fn enter(&self) -> Result<Visit> {
+        let cx = &self.config;
+        let depth = cx.depth.get();
+        if let Some(max_depth) = cx.max_depth {
+            if depth >= max_depth {
+                return Err(depth_limit_error(max_depth));
+            }
+        }
+        let value = if depth >= CYCLE_CHECK_DEPTH {
+            let mut visiting = cx.visiting.borrow_mut();
+            let visiting = visiting.get_or_insert_with(js_sys::Set::default);
+            if visiting.has(&self.value) {
+                return Err(cycle_error());
+            }
+            visiting.add(&self.value);
+            Some(self.value.clone())
+        } else {
+            None
+        };
+        cx.depth.set(depth + 1);
+        Ok(Visit {
+            cx: Rc::clone(cx),
+            value,
+        })
+    }
+
     /// Casts the internal value into an object, including support for prototype-less objects.
     /// See https://github.com/rustwasm/wasm-bindgen/issues/1366 for why we don't use `dyn_ref`.
     fn as_object_entries(&self) -> Option<Array> {
@@ -164,23 +941,87 @@ impl Deserializer {
         }
     }
 
+    /// Returns the entries of a value deserialized as a map, if it's an iterable or an object.
+    fn entries(&self) -> Result<Option<Entries>> {
+        match self.try_iter()? {
+            Some(iter) => Ok(Some(Entries::Iter(iter))),
+            None if self.config.json_values_only && !self.is_plain_object() => Ok(None),
+            None => match self.as_object_entries() {
+                Some(arr) => {
+                    check_limit(
+                        self.config.max_map_entries,
+                        arr.length(),
+                        "number of map entries",
+                    )?;
+                    Ok(Some(Entries::Object(arr)))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+
     fn is_nullish(&self) -> bool {
         self.value.loose_eq(&JsValue::NULL)
     }
 
-    fn as_bytes(&self) -> Option<Vec<u8>> {
-        let temp;
+    /// Checks whether the value is a `bigint`, unless only JSON values are accepted.
+    fn is_bigint(&self) -> bool {
+        self.value.is_bigint() && !self.config.json_values_only
+    }
 
-        let v = if let Some(v) = self.value.dyn_ref::<Uint8Array>() {
-            v
-        } else if let Some(v) = self.value.dyn_ref::<ArrayBuffer>() {
-            temp = Uint8Array::new(v);
-            &temp
+    /// Checks whether the value is an object other than an array, a `Map` or any other iterable.
+    fn is_plain_object(&self) -> bool {
+        self.value.is_object() && !Symbol::iterator().js_in(&self.value)
+    }
+
+    /// Returns an iterator if the value is iterable, unless only JSON values are accepted,
+    /// in which case arrays are the only supported iterables and are handled separately.
+    fn try_iter(&self) -> Result<Option<js_sys::IntoIter>> {
+        if self.config.json_values_only {
+            return Ok(None);
+        }
+        Ok(js_sys::try_iter(&self.value)?)
+    }
+
+    /// Checks whether the value should be treated as a missing one (`()` / `None`).
+    fn is_missing(&self) -> bool {
+        if self.config.deserialize_null_as_missing {
+            self.is_nullish()
         } else {
-            return None;
-        };
+            self.value.is_undefined()
+        }
+    }
+
+    fn as_uint8_array(&self) -> Option<Uint8Array> {
+        if let Some(v) = self.value.dyn_ref::<Uint8Array>() {
+            Some(v.clone())
+        } else {
+            self.value
+                .dyn_ref::<ArrayBuffer>()
+                .map(|v| Uint8Array::new(v))
+        }
+    }
+
+    fn as_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.as_uint8_array()?.to_vec())
+    }
 
-        Some(v.to_vec())
+    /// Passes a string value to the visitor, as an owned `String` unless deserializing in place.
+    fn visit_string<'de, V: de::Visitor<'de>>(&self, visitor: V) -> Result<V::Value> {
+        if self.config.in_place {
+            return in_place::visit_scratch_str(self.value.unchecked_ref(), visitor);
+        }
+        visitor.visit_string(self.value.as_string().unwrap_throw())
+    }
+
+    /// Fails if the value is a string longer than [`DeserializerConfig::max_string_length`].
+    fn check_string_length(&self) -> Result<()> {
+        if self.config.max_string_length.is_some() {
+            if let Some(s) = self.value.dyn_ref::<JsString>() {
+                check_limit(self.config.max_string_length, s.length(), "string length")?;
+            }
+        }
+        Ok(())
     }
 
     #[cold]
@@ -221,13 +1062,41 @@ impl Deserializer {
         None
     }
 
+    /// Unwraps boxed `Number`, `String` and `Boolean` objects if primitive coercion is enabled.
+    ///
+    /// Also rejects strings over the length limit before anything copies them.
+    fn coerced(mut self) -> Result<Self> {
+        if self.config.coerce_primitives && self.value.is_object() {
+            if self.value.is_instance_of::<Number>() {
+                self.value = self.value.unchecked_ref::<Number>().value_of().into();
+            } else if self.value.is_instance_of::<JsString>() {
+                self.value = self.value.unchecked_ref::<JsString>().value_of().into();
+            } else if self.value.is_instance_of::<Boolean>() {
+                self.value = self.value.unchecked_ref::<Boolean>().value_of().into();
+            }
+        }
+        self.check_string_length()?;
+        Ok(self)
+    }
+
+    /// Converts `bigint`s and numeric strings into integers if primitive coercion is enabled.
+    fn coerce_integer<T: TryFrom<JsValue> + std::str::FromStr>(&self) -> Option<T> {
+        if !self.config.coerce_primitives {
+            None
+        } else if self.is_bigint() {
+            T::try_from(self.value.clone()).ok()
+        } else {
+            self.value.as_string()?.parse().ok()
+        }
+    }
+
     fn deserialize_from_js_number_signed<'de, V: de::Visitor<'de>>(
         &self,
         visitor: V,
     ) -> Result<V::Value> {
-        match self.as_safe_integer() {
+        match self.as_safe_integer().or_else(|| self.coerce_integer()) {
             Some(v) => visitor.visit_i64(v),
-            _ => self.invalid_type(visitor),
+            _ => Err(self.integer_error(&visitor)),
         }
     }
 
@@ -237,23 +1106,93 @@ impl Deserializer {
     ) -> Result<V::Value> {
         match self.as_safe_integer() {
             Some(v) if v >= 0 => visitor.visit_u64(v as _),
-            _ => self.invalid_type(visitor),
+            Some(v) => Err(de::Error::invalid_value(
+                de::Unexpected::Signed(v),
+                &visitor,
+            )),
+            None => match self.coerce_integer() {
+                Some(v) => visitor.visit_u64(v),
+                None => Err(self.integer_error(&visitor)),
+            },
         }
     }
 
-    fn deserialize_from_array<'de, V: de::Visitor<'de>>(
+    /// Reports whole numbers outside of the safe range, and integer strings and BigInts if
+    /// they are accepted at all, as out of range rather than as values of the wrong type.
+    fn integer_error(&self, visitor: &dyn de::Expected) -> Error {
+        if let Some(v) = self.value.as_f64() {
+            if v.trunc() == v {
+                return de::Error::invalid_value(de::Unexpected::Float(v), visitor);
+            }
+        } else if self.config.coerce_primitives && self.is_bigint() {
+            let bigint = self.value.unchecked_ref::<js_sys::BigInt>();
+            let digits = String::from(bigint.to_string(10).unwrap_throw());
+            return Error::out_of_range(
+                de::Unexpected::Other(&format!("bigint {}", digits)),
+                visitor,
+            );
+        } else if self.config.coerce_primitives {
+            if let Some(s) = self.value.as_string() {
+                if is_integer_string(&s) {
+                    return Error::out_of_range(de::Unexpected::Str(&s), visitor);
+                }
+            }
+        }
+        self.invalid_type_(visitor)
+    }
+
+    /// Returns the value as a string if a decimal string is accepted for large integers.
+    fn as_decimal_string(&self) -> Option<String> {
+        match self.config.number_policy {
+            Some(NumberPolicy::String) => self.value.as_string(),
+            _ if self.config.coerce_primitives => self.value.as_string(),
+            _ => None,
+        }
+    }
+
+    /// Converts numeric strings into floats if primitive coercion is enabled.
+    fn coerce_float(&self) -> Option<f64> {
+        if self.config.coerce_primitives {
+            self.value.as_string()?.parse().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Checks whether safe integer `number`s are accepted for 128-bit integers.
+    fn accepts_numbers_for_128_bit(&self) -> bool {
+        self.config.number_policy.is_some() || self.config.coerce_primitives
+    }
+
+    fn deserialize_from_decimal_string<'de, T: std::str::FromStr, V: de::Visitor<'de>>(
         &self,
+        s: &str,
         visitor: V,
-        array: &Array,
+        visit: impl FnOnce(V, T) -> Result<V::Value>,
     ) -> Result<V::Value> {
-        visitor.visit_seq(SeqDeserializer::new(array.iter().map(Deserializer::from)))
+        match s.parse() {
+            Ok(v) => visit(visitor, v),
+            Err(_) if is_integer_string(s) => {
+                Err(Error::out_of_range(de::Unexpected::Str(s), &visitor))
+            }
+            Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(s), &visitor)),
+        }
     }
-}
 
-struct GetVisitorExpecting<'a, T>(&'a T);
-impl<'de, 'a, V: de::Visitor<'de>> std::fmt::Display for GetVisitorExpecting<'a, V> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.expecting(f)
+    fn deserialize_from_array<'de, V: de::Visitor<'de>>(
+        &self,
+        visitor: V,
+        array: &Array,
+    ) -> Result<V::Value> {
+        check_limit(
+            self.config.max_sequence_length,
+            array.length(),
+            "sequence length",
+        )?;
+        visitor.visit_seq(SeqAccess::new(
+            array.iter().map(Ok::<_, JsValue>),
+            Rc::clone(&self.config),
+        ))
     }
 }
 
@@ -268,12 +1207,12 @@ impl<'de> de::Deserializer<'de> for Deserializer {
             visitor.visit_unit()
         } else if let Some(v) = self.value.as_bool() {
             visitor.visit_bool(v)
-        } else if self.value.is_bigint() {
+        } else if self.is_bigint() {
             match i64::try_from(self.value) {
                 Ok(v) => visitor.visit_i64(v),
                 Err(value) => match u64::try_from(value) {
                     Ok(v) => visitor.visit_u64(v),
-                    Err(_) => Err(de::Error::custom("Couldn't deserialize i64 or u64 from a BigInt outside i64::MIN..u64::MAX bounds"))
+                    Err(_) => Err(Error::with_kind(ErrorKind::OutOfRange, "Couldn't deserialize i64 or u64 from a BigInt outside i64::MIN..u64::MAX bounds"))
                 }
             }
         } else if let Some(v) = self.value.as_f64() {
@@ -282,11 +1221,12 @@ impl<'de> de::Deserializer<'de> for Deserializer {
             } else {
                 visitor.visit_f64(v)
             }
-        } else if let Some(v) = self.value.as_string() {
-            visitor.visit_string(v)
+        } else if self.value.is_string() {
+            self.check_string_length()?;
+            self.visit_string(visitor)
         } else if Array::is_array(&self.value) {
             self.deserialize_seq(visitor)
-        } else if self.value.is_object() &&
+        } else if self.is_plain_object() {
             // The only reason we want to support objects here is because serde uses
             // `deserialize_any` for internally tagged enums
             // (see https://github.com/cloudflare/serde-wasm-bindgen/pull/4#discussion_r352245020).
@@ -297,8 +1237,6 @@ impl<'de> de::Deserializer<'de> for Deserializer {
             //
             // Hopefully we can rid of these hacks altogether once
             // https://github.com/serde-rs/serde/issues/1183 is implemented / fixed on serde side.
-            !Symbol::iterator().js_in(&self.value)
-        {
             self.deserialize_map(visitor)
         } else {
             self.invalid_type(visitor)
@@ -306,7 +1244,7 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     }
 
     fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if self.is_nullish() {
+        if self.is_missing() {
             visitor.visit_unit()
         } else {
             self.invalid_type(visitor)
@@ -322,10 +1260,11 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     }
 
     fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if let Some(v) = self.value.as_bool() {
+        let this = self.coerced()?;
+        if let Some(v) = this.value.as_bool() {
             visitor.visit_bool(v)
         } else {
-            self.invalid_type(visitor)
+            this.invalid_type(visitor)
         }
     }
 
@@ -335,10 +1274,13 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     }
 
     fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if let Some(v) = self.value.as_f64() {
+        let this = self.coerced()?;
+        if let Some(v) = this.value.as_f64() {
+            visitor.visit_f64(v)
+        } else if let Some(v) = this.coerce_float() {
             visitor.visit_f64(v)
         } else {
-            self.invalid_type(visitor)
+            this.invalid_type(visitor)
         }
     }
 
@@ -351,10 +1293,11 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     }
 
     fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if let Some(v) = self.value.as_string() {
-            visitor.visit_string(v)
+        let this = self.coerced()?;
+        if this.value.is_string() {
+            this.visit_string(visitor)
         } else {
-            self.invalid_type(visitor)
+            this.invalid_type(visitor)
         }
     }
 
@@ -362,85 +1305,98 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     // these to 64-bit methods to save some space in the generated WASM.
 
     fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_from_js_number_signed(visitor)
+        self.coerced()?.deserialize_from_js_number_signed(visitor)
     }
 
     fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_from_js_number_signed(visitor)
+        self.coerced()?.deserialize_from_js_number_signed(visitor)
     }
 
     fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_from_js_number_signed(visitor)
+        self.coerced()?.deserialize_from_js_number_signed(visitor)
     }
 
     fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_from_js_number_unsigned(visitor)
+        self.coerced()?.deserialize_from_js_number_unsigned(visitor)
     }
 
     fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_from_js_number_unsigned(visitor)
+        self.coerced()?.deserialize_from_js_number_unsigned(visitor)
     }
 
     fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_from_js_number_unsigned(visitor)
+        self.coerced()?.deserialize_from_js_number_unsigned(visitor)
     }
 
     fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        let expecting = format!("{}", GetVisitorExpecting(&visitor));
-        if expecting == "struct PreserveJsValue" {
-            NEXT_PRESERVE
-                .lock()
-                .unwrap()
-                .replace(JsValueKeeper(self.value));
-            visitor.visit_i64(0)
-        } else if self.value.is_bigint() {
-            match i64::try_from(self.value) {
+        let this = self.coerced()?;
+        if this.is_bigint() {
+            match i64::try_from(this.value) {
                 Ok(v) => visitor.visit_i64(v),
-                Err(_) => Err(de::Error::custom(
+                Err(_) => Err(Error::with_kind(
+                    ErrorKind::OutOfRange,
                     "Couldn't deserialize i64 from a BigInt outside i64::MIN..i64::MAX bounds",
                 )),
             }
+        } else if let Some(s) = this.as_decimal_string() {
+            this.deserialize_from_decimal_string(&s, visitor, V::visit_i64)
         } else {
-            self.deserialize_from_js_number_signed(visitor)
+            this.deserialize_from_js_number_signed(visitor)
         }
     }
 
     fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if self.value.is_bigint() {
-            match u64::try_from(self.value) {
+        let this = self.coerced()?;
+        if this.is_bigint() {
+            match u64::try_from(this.value) {
                 Ok(v) => visitor.visit_u64(v),
-                Err(_) => Err(de::Error::custom(
-                    "Couldn't deserialize u64 from a BigInt outside u64::MIN..u64::MAX bounds",
+                Err(_) => Err(Error::with_kind(
+                    ErrorKind::OutOfRange,
+                    "Couldn\'t deserialize u64 from a BigInt outside u64::MIN..u64::MAX bounds",
                 )),
             }
+        } else if let Some(s) = this.as_decimal_string() {
+            this.deserialize_from_decimal_string(&s, visitor, V::visit_u64)
         } else {
-            self.deserialize_from_js_number_unsigned(visitor)
+            this.deserialize_from_js_number_unsigned(visitor)
         }
     }
 
     fn deserialize_i128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if self.value.is_bigint() {
-            match i128::try_from(self.value) {
+        let this = self.coerced()?;
+        if this.is_bigint() {
+            match i128::try_from(this.value) {
                 Ok(v) => visitor.visit_i128(v),
-                Err(_) => Err(de::Error::custom(
-                    "Couldn't deserialize i128 from a BigInt outside i128::MIN..i128::MAX bounds",
+                Err(_) => Err(Error::with_kind(
+                    ErrorKind::OutOfRange,
+                    "Couldn\'t deserialize i128 from a BigInt outside i128::MIN..i128::MAX bounds",
                 )),
             }
+        } else if let Some(s) = this.as_decimal_string() {
+            this.deserialize_from_decimal_string(&s, visitor, V::visit_i128)
+        } else if this.accepts_numbers_for_128_bit() {
+            this.deserialize_from_js_number_signed(visitor)
         } else {
-            self.invalid_type(visitor)
+            this.invalid_type(visitor)
         }
     }
 
     fn deserialize_u128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if self.value.is_bigint() {
-            match u128::try_from(self.value) {
+        let this = self.coerced()?;
+        if this.is_bigint() {
+            match u128::try_from(this.value) {
                 Ok(v) => visitor.visit_u128(v),
-                Err(_) => Err(de::Error::custom(
-                    "Couldn't deserialize u128 from a BigInt outside u128::MIN..u128::MAX bounds",
+                Err(_) => Err(Error::with_kind(
+                    ErrorKind::OutOfRange,
+                    "Couldn\'t deserialize u128 from a BigInt outside u128::MIN..u128::MAX bounds",
                 )),
             }
+        } else if let Some(s) = this.as_decimal_string() {
+            this.deserialize_from_decimal_string(&s, visitor, V::visit_u128)
+        } else if this.accepts_numbers_for_128_bit() {
+            this.deserialize_from_js_number_unsigned(visitor)
         } else {
-            self.invalid_type(visitor)
+            this.invalid_type(visitor)
         }
     }
 
@@ -450,30 +1406,63 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     /// but if we get a hint that they're expected, this methods allows to avoid heap allocations
     /// of an intermediate `String` by directly converting numeric codepoints instead.
     fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if let Some(s) = self.value.dyn_ref::<JsString>() {
+        let this = self.coerced()?;
+        if let Some(s) = this.value.dyn_ref::<JsString>() {
             if let Some(c) = s.as_char() {
                 return visitor.visit_char(c);
             }
         }
-        self.invalid_type(visitor)
+        this.invalid_type(visitor)
     }
 
     // Serde can deserialize `visit_unit` into `None`, but can't deserialize arbitrary value
     // as `Some`, so we need to provide own simple implementation.
     fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if !self.is_nullish() {
+        if !self.is_missing() {
             visitor.visit_some(self)
         } else {
             visitor.visit_none()
         }
     }
 
-    /// Simply calls `visit_newtype_struct`.
+    /// Simply calls `visit_newtype_struct`, except for [`PreserveJsValue`](crate::PreserveJsValue),
+    /// which receives the value as is, and the `typed_array` adapters, which copy typed arrays
+    /// of the matching type at once.
     fn deserialize_newtype_struct<V: de::Visitor<'de>>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value> {
+        if name == PRESERVE_NAME {
+            preserve::hand_over(self.value);
+            let result = visitor.visit_unit();
+            // Make sure the value doesn't leak to anything else if the visitor didn't take it.
+            preserve::take_over();
+            return result;
+        }
+        if let Some(mut buffer) = TypedArrayBuffer::for_name(name) {
+            if self.config.json_values_only {
+                return self.deserialize_seq(visitor);
+            }
+            if ArrayBuffer::is_view(&self.value) {
+                // `length` is shared by all typed arrays.
+                let len = self.value.unchecked_ref::<Uint8Array>().length();
+                check_limit(self.config.max_sequence_length, len, "sequence length")?;
+                self.config.count_nodes(len)?;
+            }
+            if buffer.fill_from(&self.value) {
+                return buffer.visit_seq(visitor);
+            }
+            if ArrayBuffer::is_view(&self.value) {
+                let found = Object::get_prototype_of(&self.value).constructor().name();
+                return Err(de::Error::invalid_type(
+                    de::Unexpected::Other(&String::from(found)),
+                    &buffer.array_name(),
+                ));
+            }
+            // Plain arrays and other iterables are still converted one element at a time.
+            return self.deserialize_seq(visitor);
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -483,9 +1472,11 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     ///  - Any Rust sequence from Serde point of view ([`Vec`], [`HashSet`](std::collections::HashSet), etc.)
     fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         if let Some(arr) = self.value.dyn_ref::<Array>() {
+            let _visit = self.enter()?;
             self.deserialize_from_array(visitor, arr)
-        } else if let Some(iter) = js_sys::try_iter(&self.value)? {
-            visitor.visit_seq(SeqAccess { iter })
+        } else if let Some(iter) = self.try_iter()? {
+            let _visit = self.enter()?;
+            visitor.visit_seq(SeqAccess::new(iter, self.config))
         } else {
             self.invalid_type(visitor)
         }
@@ -513,12 +1504,19 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     ///  - A Rust key-value map ([`HashMap`](std::collections::HashMap), [`BTreeMap`](std::collections::BTreeMap), etc.).
     ///  - A typed Rust structure with `#[derive(Deserialize)]`.
     fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        match js_sys::try_iter(&self.value)? {
-            Some(iter) => visitor.visit_map(MapAccess::new(iter)),
-            None => match self.as_object_entries() {
-                Some(arr) => visitor.visit_map(MapDeserializer::new(arr.iter().map(convert_pair))),
-                None => self.invalid_type(visitor),
-            },
+        match self.entries()? {
+            Some(Entries::Iter(iter)) => {
+                let _visit = self.enter()?;
+                visitor.visit_map(MapAccess::new(iter, self.config))
+            }
+            Some(Entries::Object(arr)) => {
+                let _visit = self.enter()?;
+                visitor.visit_map(MapAccess::for_object(
+                    arr.iter().map(Ok::<_, JsValue>),
+                    self.config,
+                ))
+            }
+            None => self.invalid_type(visitor),
         }
     }
 
@@ -532,35 +1530,90 @@ impl<'de> de::Deserializer<'de> for Deserializer {
         fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value> {
-        let obj = if self.value.is_object() {
-            self.value.unchecked_into::<ObjectExt>()
-        } else {
+        if !self.value.is_object() {
             return self.invalid_type(visitor);
-        };
-        visitor.visit_map(ObjectAccess::new(obj, fields))
+        }
+        let _visit = self.enter()?;
+        visitor.visit_map(ObjectAccess::new(
+            self.value.unchecked_into(),
+            fields,
+            self.config,
+        ))
     }
 
     /// Here we try to be compatible with `serde-json`, which means supporting:
     ///  - `"Variant"` - gets converted to a unit variant `MyEnum::Variant`
     ///  - `{ Variant: ...payload... }` - gets converted to a `MyEnum::Variant { ...payload... }`.
+    ///
+    /// Tagged objects are expected instead of the latter if configured via
+    /// [`DeserializerConfig::enum_representation`].
     fn deserialize_enum<V: de::Visitor<'de>>(
         self,
         _name: &'static str,
         _variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value> {
+        let _visit = if self.value.is_object() {
+            Some(self.enter()?)
+        } else {
+            None
+        };
         let access = if self.value.is_string() {
             EnumAccess {
-                tag: self.value.into(),
-                payload: JsValue::UNDEFINED.into(),
+                payload: VariantAccess::new(Deserializer::new(JsValue::UNDEFINED, &self.config)),
+                tag: self,
+            }
+        } else if let EnumRepresentation::Internal { tag }
+        | EnumRepresentation::Adjacent { tag, .. } = self.config.enum_representation
+        {
+            if !self.value.is_object() {
+                return self.invalid_type(visitor);
+            }
+            let obj = self.value.unchecked_ref::<ObjectExt>();
+            let plain = inherits_object_prototype(obj);
+            let may_be_inherited = self.config.may_be_inherited(plain, tag);
+            let variant = match obj.get_field(&static_str_to_js(tag), may_be_inherited) {
+                Some(variant) if !variant.is_undefined() => variant,
+                _ => return Err(de::Error::missing_field(tag)),
+            };
+            let payload = match self.config.enum_representation {
+                EnumRepresentation::Adjacent { content, .. } => {
+                    let may_be_inherited = self.config.may_be_inherited(plain, content);
+                    let content = static_str_to_js(content);
+                    VariantAccess {
+                        payload: Deserializer::new(
+                            obj.get_field(&content, may_be_inherited)
+                                .unwrap_or(JsValue::UNDEFINED),
+                            &self.config,
+                        ),
+                        internal_tag: None,
+                        payload_key: Some(content.into()),
+                    }
+                }
+                _ => VariantAccess {
+                    payload: Deserializer::new(self.value.clone(), &self.config),
+                    internal_tag: Some(tag),
+                    payload_key: None,
+                },
+            };
+            EnumAccess {
+                tag: Deserializer::new(variant, &self.config),
+                payload,
             }
         } else if let Some(entries) = self.as_object_entries() {
             if entries.length() != 1 {
                 return Err(de::Error::invalid_length(entries.length() as _, &"1"));
             }
             let entry = entries.get(0);
-            let (tag, payload) = convert_pair(entry);
-            EnumAccess { tag, payload }
+            let (tag, payload) = convert_pair(entry, &self.config);
+            EnumAccess {
+                payload: VariantAccess {
+                    payload,
+                    internal_tag: None,
+                    payload_key: Some(tag.value.clone()),
+                },
+                tag,
+            }
         } else {
             return self.invalid_type(visitor);
         };
@@ -584,9 +1637,18 @@ impl<'de> de::Deserializer<'de> for Deserializer {
     ///  - `ArrayBuffer` - converted to an `Uint8Array` view first.
     ///  - `Uint8Array`, `Array` - copied to a newly created `Vec<u8>` on the Rust side.
     fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        if let Some(bytes) = self.as_bytes() {
-            visitor.visit_byte_buf(bytes)
-        } else if let Some(arr) = self.value.dyn_ref::<Array>() {
+        if let Some(bytes) = self
+            .as_uint8_array()
+            .filter(|_| !self.config.json_values_only)
+        {
+            check_limit(self.config.max_bytes_length, bytes.length(), "bytes length")?;
+            visitor.visit_byte_buf(bytes.to_vec())
+        } else if let Some(arr) = self
+            .value
+            .dyn_ref::<Array>()
+            .filter(|_| self.config.deserialize_bytes_from_arrays)
+        {
+            check_limit(self.config.max_bytes_length, arr.length(), "bytes length")?;
             self.deserialize_from_array(visitor, arr)
         } else {
             self.invalid_type(visitor)
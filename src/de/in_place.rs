@@ -0,0 +1,61 @@
+//! Deserialization into existing Rust values, see [`deserialize_in_place`].
+
+use super::*;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+extern "C" {
+    type TextEncoder;
+
+    #[wasm_bindgen(constructor)]
+    fn new() -> TextEncoder;
+
+    #[wasm_bindgen(method, js_name = encodeInto)]
+    fn encode_into(this: &TextEncoder, source: &JsString, destination: &Uint8Array)
+        -> EncodeResult;
+
+    type EncodeResult;
+
+    #[wasm_bindgen(method, getter)]
+    fn written(this: &EncodeResult) -> u32;
+}
+
+thread_local! {
+    static ENCODER: TextEncoder = TextEncoder::new();
+    /// Buffer that strings are decoded into, kept between calls to avoid allocations.
+    static SCRATCH: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
+
+pub(crate) fn deserialize_in_place<'de, T: de::Deserialize<'de>>(
+    place: &mut T,
+    value: JsValue,
+    config: &DeserializerConfig,
+) -> Result<()> {
+    let deserializer = Deserializer {
+        value,
+        config: Context::in_place(config.clone()),
+    };
+    T::deserialize_in_place(deserializer, place)
+}
+
+/// Passes a JS string to the visitor as a `&str` decoded into a reusable buffer, so that
+/// in-place visitors such as the one of `String` can copy it into their existing allocation.
+pub(super) fn visit_scratch_str<'de, V: de::Visitor<'de>>(
+    s: &JsString,
+    visitor: V,
+) -> Result<V::Value> {
+    // The buffer is taken out for the duration of the visit in case the visitor reenters.
+    let mut buf = SCRATCH.with(Cell::take);
+    buf.clear();
+    // Every UTF-16 code unit takes at most 3 bytes in UTF-8.
+    buf.reserve(s.length() as usize * 3);
+    // No allocations may happen while the view into linear memory is alive.
+    let written = ENCODER.with(|encoder| {
+        let view = unsafe { Uint8Array::view_mut_raw(buf.as_mut_ptr(), buf.capacity()) };
+        encoder.encode_into(s, &view).written()
+    });
+    unsafe { buf.set_len(written as usize) };
+    let result = visitor.visit_str(std::str::from_utf8(&buf).unwrap_throw());
+    SCRATCH.with(|scratch| scratch.set(buf));
+    result
+}
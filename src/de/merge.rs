@@ -0,0 +1,211 @@
+//! Partial updates of existing Rust values, see [`merge_from_value`](crate::merge_from_value).
+
+use super::*;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hash};
+
+pub(crate) fn merge<T: ?Sized + Merge>(
+    place: &mut T,
+    patch: JsValue,
+    config: &DeserializerConfig,
+) -> Result<()> {
+    place.merge_from(Patch(Deserializer {
+        value: patch,
+        config: Context::in_place(config.clone()),
+    }))
+}
+
+/// Rust values that can be partially updated from JS, see [`merge_from_value`](crate::merge_from_value).
+///
+/// Structs merge the fields present in the patch with [`Patch::merge_fields`], while
+/// values that can only be replaced as a whole use [`Patch::replace`]:
+///
+/// ```rust
+/// use serde_wasm_bindgen::{Error, Merge, Patch};
+///
+/// struct Settings {
+///     theme: String,
+///     accent: Option<String>,
+///     // Not part of the patch, so left untouched.
+///     cache: Vec<u8>,
+/// }
+///
+/// impl Merge for Settings {
+///     fn merge_from(&mut self, patch: Patch) -> Result<(), Error> {
+///         patch.merge_fields(&["theme", "accent"], |field, patch| match field {
+///             "theme" => self.theme.merge_from(patch),
+///             "accent" => self.accent.merge_from(patch),
+///             _ => Ok(()),
+///         })
+///     }
+/// }
+/// ```
+pub trait Merge {
+    /// Applies the patch to this value.
+    fn merge_from(&mut self, patch: Patch) -> Result<()>;
+}
+
+/// A JS value applied to an existing Rust value by [`Merge::merge_from`].
+pub struct Patch(Deserializer);
+
+impl Patch {
+    /// Replaces the value with the deserialized patch, reusing its allocations like
+    /// [`from_value_in_place`](crate::from_value_in_place).
+    pub fn replace<T: DeserializeOwned>(self, place: &mut T) -> Result<()> {
+        T::deserialize_in_place(self.0, place)
+    }
+
+    /// Deserializes the patch into a new value.
+    pub fn deserialize<T: DeserializeOwned>(self) -> Result<T> {
+        T::deserialize(self.0)
+    }
+
+    /// Checks whether the patch is a missing value, i.e. `undefined`, or `null` as well
+    /// unless [`DeserializerConfig::deserialize_null_as_missing`] is disabled.
+    pub fn is_missing(&self) -> bool {
+        self.0.is_missing()
+    }
+
+    /// Passes the properties of a JS object that correspond to the given struct fields
+    /// to `merge_field`, along with their patches. Fields missing from the object are skipped,
+    /// and unknown properties are passed on too if [`UnknownFields::Deny`] is configured.
+    ///
+    /// Errors returned for a field include its name in their path.
+    pub fn merge_fields(
+        self,
+        fields: &'static [&'static str],
+        mut merge_field: impl FnMut(&str, Patch) -> Result<()>,
+    ) -> Result<()> {
+        let deserializer = self.0;
+        if !deserializer.value.is_object() {
+            return Err(deserializer.invalid_type_(&"an object"));
+        }
+        let _visit = deserializer.enter()?;
+        let obj = deserializer.value.clone().unchecked_into();
+        let mut access = ObjectAccess::new(obj, fields, Rc::clone(&deserializer.config));
+        while access.next_property()? {
+            let key = access.next_key.clone();
+            access.next_value_with(|patch| merge_field(&key, Patch(patch)))?;
+        }
+        Ok(())
+    }
+
+    /// Passes each entry of a JS `Map`, another iterable of `[key, value]` pairs or an
+    /// object to `merge_entry`, with the key deserialized like the keys of a map.
+    ///
+    /// Errors returned for an entry include its key in their path.
+    pub fn merge_entries<K: DeserializeOwned>(
+        self,
+        merge_entry: impl FnMut(K, Patch) -> Result<()>,
+    ) -> Result<()> {
+        let deserializer = self.0;
+        let config = Rc::clone(&deserializer.config);
+        match deserializer.entries()? {
+            Some(Entries::Iter(iter)) => {
+                let _visit = deserializer.enter()?;
+                merge_entries(MapAccess::new(iter, config), merge_entry)
+            }
+            Some(Entries::Object(arr)) => {
+                let _visit = deserializer.enter()?;
+                let iter = arr.iter().map(Ok::<_, JsValue>);
+                merge_entries(MapAccess::for_object(iter, config), merge_entry)
+            }
+            None => Err(deserializer.invalid_type_(&"a map")),
+        }
+    }
+}
+
+fn merge_entries<I, E, K: DeserializeOwned>(
+    mut access: MapAccess<I>,
+    mut merge_entry: impl FnMut(K, Patch) -> Result<()>,
+) -> Result<()>
+where
+    I: Iterator<Item = std::result::Result<JsValue, E>>,
+    Error: From<E>,
+{
+    while let Some(key) = de::MapAccess::next_key(&mut access)? {
+        access.next_value_with(|patch| merge_entry(key, Patch(patch)))?;
+    }
+    Ok(())
+}
+
+macro_rules! replace_on_merge {
+    ($($ty:ty)*) => {
+        $(impl Merge for $ty {
+            fn merge_from(&mut self, patch: Patch) -> Result<()> {
+                patch.replace(self)
+            }
+        })*
+    };
+}
+
+replace_on_merge! {
+    bool i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64 char String
+}
+
+/// Sequences are replaced as a whole, reusing their allocations.
+impl<T: DeserializeOwned> Merge for Vec<T> {
+    fn merge_from(&mut self, patch: Patch) -> Result<()> {
+        patch.replace(self)
+    }
+}
+
+/// A missing patch clears the value, while other patches are merged into an existing one
+/// or deserialized into a new one.
+impl<T: Merge + DeserializeOwned> Merge for Option<T> {
+    fn merge_from(&mut self, patch: Patch) -> Result<()> {
+        if patch.is_missing() {
+            *self = None;
+            return Ok(());
+        }
+        match self {
+            Some(value) => value.merge_from(patch),
+            None => {
+                *self = Some(patch.deserialize()?);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<T: ?Sized + Merge> Merge for Box<T> {
+    fn merge_from(&mut self, patch: Patch) -> Result<()> {
+        T::merge_from(self, patch)
+    }
+}
+
+/// Entries present in the patch are merged into existing ones or inserted, others are kept.
+impl<K, V, S> Merge for HashMap<K, V, S>
+where
+    K: DeserializeOwned + Eq + Hash,
+    V: Merge + DeserializeOwned,
+    S: BuildHasher,
+{
+    fn merge_from(&mut self, patch: Patch) -> Result<()> {
+        patch.merge_entries(|key: K, patch| match self.get_mut(&key) {
+            Some(value) => value.merge_from(patch),
+            None => {
+                self.insert(key, patch.deserialize()?);
+                Ok(())
+            }
+        })
+    }
+}
+
+/// Entries present in the patch are merged into existing ones or inserted, others are kept.
+impl<K, V> Merge for BTreeMap<K, V>
+where
+    K: DeserializeOwned + Ord,
+    V: Merge + DeserializeOwned,
+{
+    fn merge_from(&mut self, patch: Patch) -> Result<()> {
+        patch.merge_entries(|key: K, patch| match self.get_mut(&key) {
+            Some(value) => value.merge_from(patch),
+            None => {
+                self.insert(key, patch.deserialize()?);
+                Ok(())
+            }
+        })
+    }
+}
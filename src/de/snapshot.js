@@ -0,0 +1,147 @@
+// Walks a JavaScript value once and encodes it into a snapshot for `src/de/snapshot.rs`.
+//
+// The snapshot is a single `Uint32Array` with a header (root node offset, number of words,
+// number of bytes), the nodes as 32-bit words and the UTF-8 bytes of all strings and byte
+// buffers, padded to a whole word, so that it can be copied into Wasm memory at once.
+// Objects are pushed to `refs`, so that they can still be retrieved as is.
+// Tags must be kept in sync with `snapshot.rs`.
+
+const UNDEFINED = 0;
+const NULL = 1;
+const TRUE = 2;
+const FALSE = 3;
+const NUMBER = 4; // [low bits, high bits]
+const STRING = 5; // [start, len]
+const BIGINT = 6; // [decimal string node]
+const OTHER = 7; // [ref]
+const ARRAY = 8; // [ref, n, ...items]
+const ITERABLE = 9; // [ref, n, ...items]
+const TYPED = 10; // [ref, constructor name node, n, ...items]
+const BYTES = 11; // [ref, iterable, start, len]
+const OBJECT = 12; // [ref, n, ...keys and values]
+const BOXED = 13; // [ref, primitive node, object node]
+
+const encoder = new TextEncoder();
+const f64 = new Float64Array(1);
+const u32 = new Uint32Array(f64.buffer);
+
+export function snapshot(value, refs) {
+  // Offset 0 is always `undefined`, e.g. for missing elements of `[key, value]` pairs.
+  const words = [UNDEFINED];
+  let bytes = new Uint8Array(1024);
+  let byteLen = 0;
+  const strings = new Map();
+  const seen = new Map();
+
+  const reserve = n => {
+    if (byteLen + n > bytes.length) {
+      const grown = new Uint8Array(Math.max(byteLen + n, bytes.length * 2));
+      grown.set(bytes.subarray(0, byteLen));
+      bytes = grown;
+    }
+  };
+
+  const string = s => {
+    let offset = strings.get(s);
+    if (offset === undefined) {
+      // Each UTF-16 code unit takes at most 3 bytes in UTF-8.
+      reserve(s.length * 3);
+      const start = byteLen;
+      byteLen += encoder.encodeInto(s, bytes.subarray(byteLen)).written;
+      offset = words.length;
+      words.push(STRING, start, byteLen - start);
+      strings.set(s, offset);
+    }
+    return offset;
+  };
+
+  const node = (...fields) => words.push(...fields) - fields.length;
+
+  const list = (tag, ref, value, items, ...fields) => {
+    const offset = node(tag, ref, ...fields, items.length);
+    const start = words.length;
+    words.length += items.length;
+    seen.set(value, offset);
+    for (let i = 0; i < items.length; i++) {
+      words[start + i] = walk(items[i]);
+    }
+    return offset;
+  };
+
+  const object = (ref, value) => {
+    const entries = Object.entries(value);
+    const offset = node(OBJECT, ref, entries.length);
+    const start = words.length;
+    words.length += entries.length * 2;
+    for (let i = 0; i < entries.length; i++) {
+      words[start + i * 2] = string(entries[i][0]);
+      words[start + i * 2 + 1] = walk(entries[i][1]);
+    }
+    return offset;
+  };
+
+  const walk = value => {
+    switch (typeof value) {
+      case 'undefined':
+        return 0;
+      case 'boolean':
+        return node(value ? TRUE : FALSE);
+      case 'number':
+        f64[0] = value;
+        return node(NUMBER, u32[0], u32[1]);
+      case 'string':
+        return string(value);
+      case 'bigint':
+        return node(BIGINT, string(value.toString()));
+    }
+    if (value === null) {
+      return node(NULL);
+    }
+    let offset = seen.get(value);
+    if (offset !== undefined) {
+      return offset;
+    }
+    const ref = refs.push(value) - 1;
+    if (typeof value === 'symbol') {
+      return node(OTHER, ref);
+    }
+    if (Array.isArray(value)) {
+      return list(ARRAY, ref, value, value);
+    }
+    if (value instanceof Uint8Array || value instanceof ArrayBuffer) {
+      const view = new Uint8Array(value);
+      reserve(view.length);
+      bytes.set(view, byteLen);
+      byteLen += view.length;
+      offset = node(BYTES, ref, value instanceof Uint8Array ? 1 : 0, byteLen - view.length, view.length);
+      seen.set(value, offset);
+      return offset;
+    }
+    if (ArrayBuffer.isView(value) && !(value instanceof DataView)) {
+      return list(TYPED, ref, value, Array.from(value), string(value.constructor.name));
+    }
+    if (value instanceof Number || value instanceof String || value instanceof Boolean) {
+      offset = node(BOXED, ref, 0, 0);
+      seen.set(value, offset);
+      words[offset + 2] = walk(value.valueOf());
+      words[offset + 3] = object(ref, value);
+      return offset;
+    }
+    if (typeof value[Symbol.iterator] === 'function') {
+      // Iterables other than arrays, such as `Map`s and `Set`s, are consumed once.
+      return list(ITERABLE, ref, value, Array.from(value));
+    }
+    // Reserve the object's slot before walking its properties to support cycles.
+    seen.set(value, words.length);
+    return object(ref, value);
+  };
+
+  const root = walk(value);
+  const out = new Uint32Array(3 + words.length + Math.ceil(byteLen / 4));
+  out[0] = root;
+  out[1] = words.length;
+  out[2] = byteLen;
+  out.set(words, 3);
+  new Uint8Array(out.buffer, (3 + words.length) * 4, byteLen).set(bytes.subarray(0, byteLen));
+  return out;
+}
@@ -0,0 +1,1197 @@
+//! Batched deserialization backend.
+//!
+//! A bundled JS function walks the input once and encodes it into a snapshot, see
+//! `snapshot.js`. The snapshot is then deserialized without any further calls to JS,
+//! except for retrieving values for [`PreserveJsValue`](crate::PreserveJsValue) and
+//! describing unexpected objects in error messages.
+
+use super::*;
+use js_sys::Uint32Array;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen(module = "/src/de/snapshot.js")]
+extern "C" {
+    #[wasm_bindgen(catch)]
+    fn snapshot(value: &JsValue, refs: &Array) -> std::result::Result<Uint32Array, JsValue>;
+}
+
+// Node tags, see `snapshot.js` for their layout.
+const UNDEFINED: u32 = 0;
+const NULL: u32 = 1;
+const TRUE: u32 = 2;
+const FALSE: u32 = 3;
+const NUMBER: u32 = 4;
+const STRING: u32 = 5;
+const BIGINT: u32 = 6;
+const ARRAY: u32 = 8;
+const ITERABLE: u32 = 9;
+const TYPED: u32 = 10;
+const BYTES: u32 = 11;
+const OBJECT: u32 = 12;
+const BOXED: u32 = 13;
+
+/// Number of words before the nodes: root offset, number of words and number of bytes.
+const HEADER_LEN: usize = 3;
+
+struct Snapshot {
+    root: u32,
+    /// The whole snapshot as encoded by `snapshot.js`, including the header and the bytes.
+    data: Vec<u32>,
+    word_count: usize,
+    byte_len: usize,
+    /// Objects of the input, so that they can be retrieved as is.
+    refs: Array,
+    config: DeserializerConfig,
+    /// Offsets of containers that are currently being deserialized.
+    visiting: RefCell<Vec<u32>>,
+    nodes: Cell<u32>,
+    path: CurrentPath,
+}
+
+impl Snapshot {
+    fn take(value: &JsValue, config: &DeserializerConfig) -> Result<Self> {
+        let refs = Array::new();
+        let array = snapshot(value, &refs)?;
+        let mut data = vec![0; array.length() as usize];
+        array.copy_to(&mut data);
+        Ok(Self {
+            root: data[0],
+            word_count: data[1] as usize,
+            byte_len: data[2] as usize,
+            data,
+            refs,
+            config: config.clone(),
+            visiting: RefCell::new(Vec::new()),
+            nodes: Cell::new(0),
+            path: CurrentPath::new(config),
+        })
+    }
+
+    fn words(&self) -> &[u32] {
+        &self.data[HEADER_LEN..HEADER_LEN + self.word_count]
+    }
+
+    fn bytes(&self) -> &[u8] {
+        let words = &self.data[HEADER_LEN + self.word_count..];
+        // SAFETY: the words are initialized and `u8` has no alignment requirements. Both Wasm
+        // and JS engines are little-endian, so the bytes are in the order `snapshot.js` wrote them.
+        let bytes =
+            unsafe { std::slice::from_raw_parts(words.as_ptr().cast::<u8>(), words.len() * 4) };
+        &bytes[..self.byte_len]
+    }
+
+    /// Counts values that are about to be deserialized towards [`DeserializerConfig::max_nodes`].
+    fn count_nodes(&self, n: u32) -> Result<()> {
+        let nodes = self.nodes.get().saturating_add(n);
+        check_limit(self.config.max_nodes, nodes, "number of values")?;
+        self.nodes.set(nodes);
+        Ok(())
+    }
+}
+
+/// Converts a JS value into a Rust type via a snapshot taken in a single call to JS.
+pub(crate) fn deserialize_batched<T: de::DeserializeOwned>(
+    value: &JsValue,
+    config: &DeserializerConfig,
+) -> Result<T> {
+    let snapshot = Snapshot::take(value, config)?;
+    T::deserialize(Node::new(&snapshot, snapshot.root))
+}
+
+/// What a node of the snapshot represents.
+enum Kind<'s> {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    BigInt(&'s str),
+    String(&'s str),
+    /// Symbols and other values that can only be retrieved as is.
+    Other,
+    Array(&'s [u32]),
+    /// Elements of an iterable other than an array, such as a `Map` or a `Set`.
+    Iterable(&'s [u32]),
+    /// Elements and constructor name of a typed array other than `Uint8Array`.
+    Typed(&'s str, &'s [u32]),
+    /// Contents of a `Uint8Array` (iterable) or an `ArrayBuffer` (not iterable).
+    Bytes(&'s [u8], bool),
+    /// Keys and values of own enumerable properties of any other object.
+    Object(&'s [u32]),
+    /// A boxed `Number`, `String` or `Boolean` with its primitive value and properties.
+    Boxed(Node<'s>, Node<'s>),
+}
+
+/// Keys and values of the own enumerable properties of an object node, in property order.
+struct Entries<'s> {
+    snapshot: &'s Snapshot,
+    pairs: std::slice::ChunksExact<'s, u32>,
+    hidden_key: Option<&'static str>,
+}
+
+impl<'s> Iterator for Entries<'s> {
+    type Item = (&'s str, Node<'s>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for pair in &mut self.pairs {
+            let key = Node::new(self.snapshot, pair[0]).as_str().unwrap_throw();
+            if Some(key) != self.hidden_key {
+                return Some((key, Node::new(self.snapshot, pair[1])));
+            }
+        }
+        None
+    }
+}
+
+/// A [`serde::Deserializer`] for a node of a [`Snapshot`].
+#[derive(Clone, Copy)]
+struct Node<'s> {
+    snapshot: &'s Snapshot,
+    offset: u32,
+    /// A property that is not visible when the node is deserialized as an object,
+    /// such as the tag of an internally tagged enum.
+    hidden_key: Option<&'static str>,
+}
+
+impl<'s> Node<'s> {
+    const fn new(snapshot: &'s Snapshot, offset: u32) -> Self {
+        Self {
+            snapshot,
+            offset,
+            hidden_key: None,
+        }
+    }
+
+    const fn config(&self) -> &'s DeserializerConfig {
+        &self.snapshot.config
+    }
+
+    /// Starts deserializing the contents of a container, checking for cycles and the depth limit.
+    fn enter(&self) -> Result<Visit<'s>> {
+        let mut visiting = self.snapshot.visiting.borrow_mut();
+        if let Some(max_depth) = self.config().max_depth {
+            if visiting.len() >= max_depth as usize {
+                return Err(depth_limit_error(max_depth));
+            }
+        }
+        // A node with a hidden key is the object of an internally tagged enum being entered again.
+        let tracked = visiting
+            .get(CYCLE_CHECK_DEPTH as usize..)
+            .unwrap_or_default();
+        if self.hidden_key.is_none() && tracked.contains(&self.offset) {
+            return Err(cycle_error());
+        }
+        visiting.push(self.offset);
+        Ok(Visit(self.snapshot))
+    }
+
+    fn word(&self, i: u32) -> u32 {
+        self.snapshot.words()[(self.offset + i) as usize]
+    }
+
+    fn child(&self, i: u32) -> Self {
+        Self::new(self.snapshot, self.word(i))
+    }
+
+    /// Returns `n` words starting at `i`.
+    fn words(&self, i: u32, n: u32) -> &'s [u32] {
+        let start = (self.offset + i) as usize;
+        &self.snapshot.words()[start..start + n as usize]
+    }
+
+    /// Returns the items of a list that stores its length at `i`.
+    fn items(&self, i: u32) -> &'s [u32] {
+        self.words(i + 1, self.word(i))
+    }
+
+    fn data(&self, start: u32, len: u32) -> &'s [u8] {
+        &self.snapshot.bytes()[start as usize..(start + len) as usize]
+    }
+
+    /// Returns what the node represents, treating non-JSON values as [`Kind::Other`]
+    /// if only JSON values are accepted.
+    fn kind(&self) -> Kind<'s> {
+        match self.raw_kind() {
+            Kind::BigInt(_) | Kind::Iterable(_) | Kind::Typed(..) | Kind::Bytes(..)
+                if self.config().json_values_only =>
+            {
+                Kind::Other
+            }
+            kind => kind,
+        }
+    }
+
+    fn raw_kind(&self) -> Kind<'s> {
+        match self.word(0) {
+            UNDEFINED => Kind::Undefined,
+            NULL => Kind::Null,
+            TRUE => Kind::Bool(true),
+            FALSE => Kind::Bool(false),
+            NUMBER => Kind::Number(f64::from_bits(
+                u64::from(self.word(2)) << 32 | u64::from(self.word(1)),
+            )),
+            STRING => Kind::String(
+                std::str::from_utf8(self.data(self.word(1), self.word(2))).unwrap_throw(),
+            ),
+            BIGINT => Kind::BigInt(self.child(1).as_str().unwrap_throw()),
+            ARRAY => Kind::Array(self.items(2)),
+            ITERABLE => Kind::Iterable(self.items(2)),
+            TYPED => Kind::Typed(self.child(2).as_str().unwrap_throw(), self.items(3)),
+            BYTES => Kind::Bytes(self.data(self.word(3), self.word(4)), self.word(2) != 0),
+            OBJECT => Kind::Object(self.words(3, self.word(2) * 2)),
+            BOXED => Kind::Boxed(self.child(2), self.child(3)),
+            _ => Kind::Other,
+        }
+    }
+
+    /// Retrieves the original JS value.
+    fn to_js(self) -> JsValue {
+        match self.raw_kind() {
+            Kind::Undefined => JsValue::UNDEFINED,
+            Kind::Null => JsValue::NULL,
+            Kind::Bool(v) => v.into(),
+            Kind::Number(v) => v.into(),
+            Kind::String(v) => v.into(),
+            Kind::BigInt(v) => js_sys::BigInt::new(&v.into()).unwrap_throw().into(),
+            _ => self.snapshot.refs.get(self.word(1)),
+        }
+    }
+
+    fn as_str(&self) -> Option<&'s str> {
+        match self.kind() {
+            Kind::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn as_bytes(&self) -> Option<&'s [u8]> {
+        match self.kind() {
+            Kind::Bytes(v, _) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the keys and values of own enumerable properties if the node is an object.
+    fn object_words(&self) -> Option<&'s [u32]> {
+        match self.kind() {
+            Kind::Object(words) => Some(words),
+            Kind::Boxed(_, object) => object.object_words(),
+            Kind::Array(_) | Kind::Iterable(_) | Kind::Typed(..) | Kind::Bytes(..) => Some(&[]),
+            _ => None,
+        }
+    }
+
+    fn entries(&self) -> Option<Entries<'s>> {
+        Some(Entries {
+            snapshot: self.snapshot,
+            pairs: self.object_words()?.chunks_exact(2),
+            hidden_key: self.hidden_key,
+        })
+    }
+
+    fn get(&self, key: &str) -> Option<Node<'s>> {
+        self.entries()?.find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Returns the elements if the node is iterable.
+    fn elements(&self) -> Option<SeqItems<'s>> {
+        match self.kind() {
+            Kind::Array(items) | Kind::Iterable(items) | Kind::Typed(_, items) => {
+                Some(SeqItems::Nodes(items.iter()))
+            }
+            Kind::Bytes(bytes, true) => Some(SeqItems::Bytes(bytes.iter())),
+            _ => None,
+        }
+    }
+
+    fn is_nullish(&self) -> bool {
+        matches!(self.kind(), Kind::Undefined | Kind::Null)
+    }
+
+    fn is_missing(&self) -> bool {
+        if self.config().deserialize_null_as_missing {
+            self.is_nullish()
+        } else {
+            matches!(self.kind(), Kind::Undefined)
+        }
+    }
+
+    #[cold]
+    fn invalid_type_(&self, visitor: &dyn de::Expected) -> Error {
+        let string;
+
+        let unexpected = match self.kind() {
+            Kind::Undefined | Kind::Null => de::Unexpected::Unit,
+            Kind::Bool(v) => de::Unexpected::Bool(v),
+            Kind::Number(v) => de::Unexpected::Float(v),
+            Kind::String(v) => de::Unexpected::Str(v),
+            Kind::Bytes(v, _) => de::Unexpected::Bytes(v),
+            _ => {
+                string = format!("{:?}", self.to_js());
+                de::Unexpected::Other(&string)
+            }
+        };
+
+        de::Error::invalid_type(unexpected, visitor)
+    }
+
+    fn invalid_type<'de, V: de::Visitor<'de>>(&self, visitor: V) -> Result<V::Value> {
+        Err(self.invalid_type_(&visitor))
+    }
+
+    fn as_safe_integer(&self) -> Option<i64> {
+        match self.kind() {
+            Kind::Number(v) if v.trunc() == v && v.abs() <= Number::MAX_SAFE_INTEGER => {
+                Some(v as i64)
+            }
+            _ => None,
+        }
+    }
+
+    /// Unwraps boxed primitives if primitive coercion is enabled.
+    fn coerced(self) -> Result<Self> {
+        let this = match self.kind() {
+            Kind::Boxed(primitive, _) if self.config().coerce_primitives => primitive,
+            _ => self,
+        };
+        this.check_string_length()?;
+        Ok(this)
+    }
+
+    /// Fails if the node is a string longer than [`DeserializerConfig::max_string_length`].
+    fn check_string_length(&self) -> Result<()> {
+        let limit = self.config().max_string_length;
+        if let (Some(_), Kind::String(s)) = (limit, self.kind()) {
+            // Same as the `length` of the JS string.
+            let len = s.chars().map(|c| c.len_utf16() as u32).sum();
+            check_limit(limit, len, "string length")?;
+        }
+        Ok(())
+    }
+
+    /// Converts `bigint`s and numeric strings into integers if primitive coercion is enabled.
+    fn coerce_integer<T: std::str::FromStr>(&self) -> Option<T> {
+        if !self.config().coerce_primitives {
+            return None;
+        }
+        match self.kind() {
+            Kind::BigInt(v) | Kind::String(v) => v.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn deserialize_from_js_number_signed<'de, V: de::Visitor<'de>>(
+        &self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.as_safe_integer().or_else(|| self.coerce_integer()) {
+            Some(v) => visitor.visit_i64(v),
+            _ => Err(self.integer_error(&visitor)),
+        }
+    }
+
+    fn deserialize_from_js_number_unsigned<'de, V: de::Visitor<'de>>(
+        &self,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.as_safe_integer() {
+            Some(v) if v >= 0 => visitor.visit_u64(v as _),
+            Some(v) => Err(de::Error::invalid_value(
+                de::Unexpected::Signed(v),
+                &visitor,
+            )),
+            None => match self.coerce_integer() {
+                Some(v) => visitor.visit_u64(v),
+                None => Err(self.integer_error(&visitor)),
+            },
+        }
+    }
+
+    /// Same as `Deserializer::integer_error`.
+    fn integer_error(&self, visitor: &dyn de::Expected) -> Error {
+        match self.kind() {
+            Kind::Number(v) if v.trunc() == v => {
+                de::Error::invalid_value(de::Unexpected::Float(v), visitor)
+            }
+            Kind::String(s) if self.config().coerce_primitives && is_integer_string(s) => {
+                Error::out_of_range(de::Unexpected::Str(s), visitor)
+            }
+            _ => self.invalid_type_(visitor),
+        }
+    }
+
+    /// Returns the value as a string if a decimal string is accepted for large integers.
+    fn as_decimal_string(&self) -> Option<&'s str> {
+        match self.config().number_policy {
+            Some(NumberPolicy::String) => self.as_str(),
+            _ if self.config().coerce_primitives => self.as_str(),
+            _ => None,
+        }
+    }
+
+    /// Converts numeric strings into floats if primitive coercion is enabled.
+    fn coerce_float(&self) -> Option<f64> {
+        if self.config().coerce_primitives {
+            self.as_str()?.parse().ok()
+        } else {
+            None
+        }
+    }
+
+    const fn accepts_numbers_for_128_bit(&self) -> bool {
+        self.config().number_policy.is_some() || self.config().coerce_primitives
+    }
+
+    fn deserialize_from_decimal_string<'de, T: std::str::FromStr, V: de::Visitor<'de>>(
+        &self,
+        s: &str,
+        visitor: V,
+        visit: impl FnOnce(V, T) -> Result<V::Value>,
+    ) -> Result<V::Value> {
+        match s.parse() {
+            Ok(v) => visit(visitor, v),
+            Err(_) if is_integer_string(s) => {
+                Err(Error::out_of_range(de::Unexpected::Str(s), &visitor))
+            }
+            Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(s), &visitor)),
+        }
+    }
+
+    /// Parses a `bigint` into the target type, failing with the given message if it doesn't fit.
+    fn deserialize_from_bigint<'de, T: std::str::FromStr, V: de::Visitor<'de>>(
+        s: &str,
+        visitor: V,
+        visit: impl FnOnce(V, T) -> Result<V::Value>,
+        message: &'static str,
+    ) -> Result<V::Value> {
+        match s.parse() {
+            Ok(v) => visit(visitor, v),
+            Err(_) => Err(Error::with_kind(ErrorKind::OutOfRange, message)),
+        }
+    }
+
+    fn deserialize_map_from_entries<V: de::Visitor<'s>>(self, visitor: V) -> Result<V::Value> {
+        match self.entries() {
+            Some(entries) => {
+                let len = self
+                    .object_words()
+                    .map_or(0, |words| words.len() as u32 / 2);
+                check_limit(self.config().max_map_entries, len, "number of map entries")?;
+                let _visit = self.enter()?;
+                visitor.visit_map(MapAccess::new(
+                    entries.map(|(key, value)| (Key::Str(key), value)),
+                ))
+            }
+            None => self.invalid_type(visitor),
+        }
+    }
+}
+
+/// Marks a container as being deserialized until dropped.
+struct Visit<'s>(&'s Snapshot);
+
+impl Drop for Visit<'_> {
+    fn drop(&mut self) {
+        self.0.visiting.borrow_mut().pop();
+    }
+}
+
+enum SeqItems<'s> {
+    Nodes(std::slice::Iter<'s, u32>),
+    Bytes(std::slice::Iter<'s, u8>),
+}
+
+impl SeqItems<'_> {
+    /// Returns the number of remaining items.
+    fn len(&self) -> usize {
+        match self {
+            SeqItems::Nodes(iter) => iter.len(),
+            SeqItems::Bytes(iter) => iter.len(),
+        }
+    }
+}
+
+struct SeqAccess<'s> {
+    snapshot: &'s Snapshot,
+    items: SeqItems<'s>,
+    idx: u32,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        let limit = self.snapshot.config.max_sequence_length;
+        if self.items.len() > 0 {
+            check_limit(limit, self.idx + 1, "sequence length")?;
+            self.snapshot.count_nodes(1)?;
+        }
+        let idx = self.idx;
+        let path = &self.snapshot.path;
+        let result = match &mut self.items {
+            SeqItems::Nodes(iter) => match iter.next() {
+                Some(&offset) => {
+                    let node = Node::new(self.snapshot, offset);
+                    path.nested(|| PathSegment::Index(idx), || seed.deserialize(node))
+                }
+                None => return Ok(None),
+            },
+            SeqItems::Bytes(iter) => match iter.next() {
+                Some(&byte) => path.nested(
+                    || PathSegment::Index(idx),
+                    || seed.deserialize(u64::from(byte).into_deserializer()),
+                ),
+                None => return Ok(None),
+            },
+        };
+        self.idx += 1;
+        result.map(Some)
+    }
+}
+
+/// The key of a map entry or the tag of an enum, which is either a property name or any value.
+enum Key<'s> {
+    Str(&'s str),
+    Node(Node<'s>),
+}
+
+impl<'s> Key<'s> {
+    fn as_str(&self) -> Option<&'s str> {
+        match self {
+            Key::Str(key) => Some(key),
+            Key::Node(node) => node.as_str(),
+        }
+    }
+
+    fn deserialize<T: de::DeserializeSeed<'s>>(self, seed: T) -> Result<T::Value> {
+        match self {
+            Key::Str(key) => seed.deserialize(PropertyKey(key)),
+            Key::Node(node) => seed.deserialize(node),
+        }
+    }
+}
+
+struct MapAccess<'s, I> {
+    iter: I,
+    idx: u32,
+    /// Key of the current entry, used to report errors in its value.
+    next_key: Option<&'s str>,
+    next_value: Option<Node<'s>>,
+}
+
+impl<'s, I> MapAccess<'s, I> {
+    const fn new(iter: I) -> Self {
+        Self {
+            iter,
+            idx: 0,
+            next_key: None,
+            next_value: None,
+        }
+    }
+}
+
+impl<'de, I> de::MapAccess<'de> for MapAccess<'de, I>
+where
+    I: Iterator<Item = (Key<'de>, Node<'de>)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        debug_assert!(self.next_value.is_none());
+
+        Ok(match self.iter.next() {
+            Some((key, value)) => {
+                let limit = value.config().max_map_entries;
+                check_limit(limit, self.idx + 1, "number of map entries")?;
+                value.snapshot.count_nodes(2)?;
+                self.idx += 1;
+                self.next_key = key.as_str();
+                self.next_value = Some(value);
+                let idx = self.idx - 1;
+                Some(
+                    value
+                        .snapshot
+                        .path
+                        .nested(|| PathSegment::MapKey(idx), || key.deserialize(seed))?,
+                )
+            }
+            None => None,
+        })
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self.next_value.take().unwrap_throw();
+        let (key, idx) = (self.next_key, self.idx - 1);
+        value.snapshot.path.nested(
+            || match key {
+                Some(key) => PathSegment::Field(key.to_owned()),
+                None => PathSegment::MapValue(idx),
+            },
+            || seed.deserialize(value),
+        )
+    }
+}
+
+/// Splits an element of an iterable into a `[key, value]` pair.
+fn pair(node: Node) -> (Key, Node) {
+    let snapshot = node.snapshot;
+    let (key, value) = match node.kind() {
+        Kind::Array(items) => (
+            items.first().copied().unwrap_or(0),
+            items.get(1).copied().unwrap_or(0),
+        ),
+        _ => (0, 0),
+    };
+    (
+        Key::Node(Node::new(snapshot, key)),
+        Node::new(snapshot, value),
+    )
+}
+
+/// Provides [`serde::de::MapAccess`] for the fields of a struct.
+///
+/// Unlike its counterpart for live JS objects, it walks the properties in order, since
+/// the snapshot already lists them, instead of looking up each field of the struct.
+struct ObjectAccess<'s> {
+    obj: Node<'s>,
+    fields: &'static [&'static str],
+    entries: Entries<'s>,
+    /// A property that is neither a field nor unknown, such as the tag of an internally tagged enum.
+    ignored_key: Option<&'static str>,
+    /// Key of the current property, used to report errors in its value.
+    next_key: &'s str,
+    next_value: Option<Node<'s>>,
+}
+
+impl<'s> ObjectAccess<'s> {
+    /// Creates an accessor for a node that is known to have [`Node::entries`].
+    fn new(obj: Node<'s>, fields: &'static [&'static str]) -> Self {
+        Self {
+            obj,
+            fields,
+            entries: obj.entries().unwrap_throw(),
+            ignored_key: None,
+            next_key: "",
+            next_value: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for ObjectAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        debug_assert!(self.next_value.is_none());
+
+        for (key, value) in &mut self.entries {
+            if self.ignored_key == Some(key) {
+                continue;
+            }
+            if !self.fields.contains(&key) {
+                match &self.obj.config().unknown_fields {
+                    UnknownFields::Ignore => continue,
+                    UnknownFields::Deny => {}
+                    UnknownFields::Warn(callback) => {
+                        self.obj.snapshot.path.report(&**callback, key.to_owned());
+                        continue;
+                    }
+                }
+            }
+            self.obj.snapshot.count_nodes(1)?;
+            self.next_key = key;
+            self.next_value = Some(value);
+            return Ok(Some(seed.deserialize(str_deserializer(key))?));
+        }
+
+        Ok(None)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self.next_value.take().unwrap_throw();
+        let key = self.next_key;
+        value.snapshot.path.nested(
+            || PathSegment::Field(key.to_owned()),
+            || seed.deserialize(value),
+        )
+    }
+}
+
+/// Provides [`serde::de::EnumAccess`] for the tag and the payload of an enum variant.
+struct EnumAccess<'s> {
+    tag: Key<'s>,
+    payload: VariantAccess<'s>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = VariantAccess<'de>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant)> {
+        Ok((self.tag.deserialize(seed)?, self.payload))
+    }
+}
+
+/// Provides [`serde::de::VariantAccess`] for the payload of an enum variant.
+struct VariantAccess<'s> {
+    payload: Node<'s>,
+    /// Name of the tag property if the payload is the internally tagged object itself.
+    internal_tag: Option<&'static str>,
+    /// Key of the property holding the payload, used to report errors in it.
+    payload_key: Option<&'s str>,
+}
+
+impl<'s> VariantAccess<'s> {
+    /// Deserializes the payload, adding the key of the property holding it to the path.
+    fn with_path<T>(&self, f: impl FnOnce(Node<'s>) -> Result<T>) -> Result<T> {
+        match self.payload_key {
+            Some(key) => self
+                .payload
+                .snapshot
+                .path
+                .nested(|| PathSegment::Field(key.to_owned()), || f(self.payload)),
+            None => f(self.payload),
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.internal_tag {
+            // Any other properties of the tagged object are ignored.
+            Some(_) => Ok(()),
+            None => self.with_path(de::Deserialize::deserialize),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        match self.internal_tag {
+            // Hide the tag so that it's not visible to maps and strict structs.
+            Some(tag) => seed.deserialize(Node {
+                hidden_key: Some(tag),
+                ..self.payload
+            }),
+            None => self.with_path(|payload| seed.deserialize(payload)),
+        }
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        match self.internal_tag {
+            Some(_) => Err(de::Error::custom(
+                "tuple variants can't be represented with an internal tag",
+            )),
+            None => {
+                self.with_path(|payload| de::Deserializer::deserialize_tuple(payload, len, visitor))
+            }
+        }
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.internal_tag {
+            Some(tag) => {
+                if self.payload.entries().is_none() {
+                    return self.payload.invalid_type(visitor);
+                }
+                let mut access = ObjectAccess::new(self.payload, fields);
+                access.ignored_key = Some(tag);
+                visitor.visit_map(access)
+            }
+            None => self.with_path(|payload| {
+                de::Deserializer::deserialize_struct(payload, "", fields, visitor)
+            }),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Node<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.kind() {
+            Kind::Undefined | Kind::Null => visitor.visit_unit(),
+            Kind::Bool(v) => visitor.visit_bool(v),
+            Kind::BigInt(v) => match v.parse::<i64>() {
+                Ok(v) => visitor.visit_i64(v),
+                Err(_) => match v.parse::<u64>() {
+                    Ok(v) => visitor.visit_u64(v),
+                    Err(_) => Err(Error::with_kind(ErrorKind::OutOfRange, "Couldn't deserialize i64 or u64 from a BigInt outside i64::MIN..u64::MAX bounds")),
+                },
+            },
+            Kind::Number(v) => match self.as_safe_integer() {
+                Some(v) => visitor.visit_i64(v),
+                None => visitor.visit_f64(v),
+            },
+            Kind::String(v) => {
+                self.check_string_length()?;
+                visitor.visit_borrowed_str(v)
+            }
+            Kind::Array(_) => self.deserialize_seq(visitor),
+            // Like with the `Deserializer`, only plain objects are supported here, because
+            // Serde uses `deserialize_any` for internally tagged enums.
+            Kind::Object(_) | Kind::Boxed(..) => self.deserialize_map_from_entries(visitor),
+            Kind::Bytes(_, false) => self.deserialize_map_from_entries(visitor),
+            _ => self.invalid_type(visitor),
+        }
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.is_missing() {
+            visitor.visit_unit()
+        } else {
+            self.invalid_type(visitor)
+        }
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let this = self.coerced()?;
+        match this.kind() {
+            Kind::Bool(v) => visitor.visit_bool(v),
+            _ => this.invalid_type(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let this = self.coerced()?;
+        if let Kind::Number(v) = this.kind() {
+            visitor.visit_f64(v)
+        } else if let Some(v) = this.coerce_float() {
+            visitor.visit_f64(v)
+        } else {
+            this.invalid_type(visitor)
+        }
+    }
+
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let this = self.coerced()?;
+        match this.as_str() {
+            Some(v) => visitor.visit_borrowed_str(v),
+            None => this.invalid_type(visitor),
+        }
+    }
+
+    fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.coerced()?.deserialize_from_js_number_signed(visitor)
+    }
+
+    fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.coerced()?.deserialize_from_js_number_signed(visitor)
+    }
+
+    fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.coerced()?.deserialize_from_js_number_signed(visitor)
+    }
+
+    fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.coerced()?.deserialize_from_js_number_unsigned(visitor)
+    }
+
+    fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.coerced()?.deserialize_from_js_number_unsigned(visitor)
+    }
+
+    fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.coerced()?.deserialize_from_js_number_unsigned(visitor)
+    }
+
+    fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let this = self.coerced()?;
+        if let Kind::BigInt(s) = this.kind() {
+            Self::deserialize_from_bigint(
+                s,
+                visitor,
+                V::visit_i64,
+                "Couldn't deserialize i64 from a BigInt outside i64::MIN..i64::MAX bounds",
+            )
+        } else if let Some(s) = this.as_decimal_string() {
+            this.deserialize_from_decimal_string(s, visitor, V::visit_i64)
+        } else {
+            this.deserialize_from_js_number_signed(visitor)
+        }
+    }
+
+    fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let this = self.coerced()?;
+        if let Kind::BigInt(s) = this.kind() {
+            Self::deserialize_from_bigint(
+                s,
+                visitor,
+                V::visit_u64,
+                "Couldn't deserialize u64 from a BigInt outside u64::MIN..u64::MAX bounds",
+            )
+        } else if let Some(s) = this.as_decimal_string() {
+            this.deserialize_from_decimal_string(s, visitor, V::visit_u64)
+        } else {
+            this.deserialize_from_js_number_unsigned(visitor)
+        }
+    }
+
+    fn deserialize_i128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let this = self.coerced()?;
+        if let Kind::BigInt(s) = this.kind() {
+            Self::deserialize_from_bigint(
+                s,
+                visitor,
+                V::visit_i128,
+                "Couldn't deserialize i128 from a BigInt outside i128::MIN..i128::MAX bounds",
+            )
+        } else if let Some(s) = this.as_decimal_string() {
+            this.deserialize_from_decimal_string(s, visitor, V::visit_i128)
+        } else if this.accepts_numbers_for_128_bit() {
+            this.deserialize_from_js_number_signed(visitor)
+        } else {
+            this.invalid_type(visitor)
+        }
+    }
+
+    fn deserialize_u128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let this = self.coerced()?;
+        if let Kind::BigInt(s) = this.kind() {
+            Self::deserialize_from_bigint(
+                s,
+                visitor,
+                V::visit_u128,
+                "Couldn't deserialize u128 from a BigInt outside u128::MIN..u128::MAX bounds",
+            )
+        } else if let Some(s) = this.as_decimal_string() {
+            this.deserialize_from_decimal_string(s, visitor, V::visit_u128)
+        } else if this.accepts_numbers_for_128_bit() {
+            this.deserialize_from_js_number_unsigned(visitor)
+        } else {
+            this.invalid_type(visitor)
+        }
+    }
+
+    fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let this = self.coerced()?;
+        if let Some(s) = this.as_str() {
+            let mut chars = s.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                return visitor.visit_char(c);
+            }
+        }
+        this.invalid_type(visitor)
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if !self.is_missing() {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        if name == PRESERVE_NAME {
+            preserve::hand_over(self.to_js());
+            let result = visitor.visit_unit();
+            preserve::take_over();
+            return result;
+        }
+        if let Some(buffer) = TypedArrayBuffer::for_name(name) {
+            let found = match self.kind() {
+                Kind::Typed(found, _) => found,
+                Kind::Bytes(_, true) => "Uint8Array",
+                _ => return self.deserialize_seq(visitor),
+            };
+            if found != buffer.array_name() {
+                return Err(de::Error::invalid_type(
+                    de::Unexpected::Other(found),
+                    &buffer.array_name(),
+                ));
+            }
+            return self.deserialize_seq(visitor);
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.elements() {
+            Some(items) => {
+                let len = items.len() as u32;
+                check_limit(self.config().max_sequence_length, len, "sequence length")?;
+                let _visit = self.enter()?;
+                visitor.visit_seq(SeqAccess {
+                    snapshot: self.snapshot,
+                    items,
+                    idx: 0,
+                })
+            }
+            None => self.invalid_type(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let snapshot = self.snapshot;
+        match self.kind() {
+            Kind::Array(_) if self.config().json_values_only => self.invalid_type(visitor),
+            Kind::Array(items) | Kind::Iterable(items) | Kind::Typed(_, items) => {
+                let len = items.len() as u32;
+                check_limit(self.config().max_map_entries, len, "number of map entries")?;
+                let _visit = self.enter()?;
+                visitor.visit_map(MapAccess::new(
+                    items
+                        .iter()
+                        .map(move |&offset| pair(Node::new(snapshot, offset))),
+                ))
+            }
+            _ => self.deserialize_map_from_entries(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if self.entries().is_none() {
+            return self.invalid_type(visitor);
+        }
+        let _visit = self.enter()?;
+        visitor.visit_map(ObjectAccess::new(self, fields))
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let _visit = match self.entries() {
+            Some(_) => Some(self.enter()?),
+            None => None,
+        };
+        let access = if self.as_str().is_some() {
+            EnumAccess {
+                tag: Key::Node(self),
+                payload: VariantAccess {
+                    payload: Node::new(self.snapshot, 0),
+                    internal_tag: None,
+                    payload_key: None,
+                },
+            }
+        } else if let EnumRepresentation::Internal { tag }
+        | EnumRepresentation::Adjacent { tag, .. } = self.config().enum_representation
+        {
+            if self.entries().is_none() {
+                return self.invalid_type(visitor);
+            }
+            let variant = match self.get(tag) {
+                Some(variant) if !matches!(variant.kind(), Kind::Undefined) => variant,
+                _ => return Err(de::Error::missing_field(tag)),
+            };
+            let payload = match self.config().enum_representation {
+                EnumRepresentation::Adjacent { content, .. } => VariantAccess {
+                    payload: self
+                        .get(content)
+                        .unwrap_or_else(|| Node::new(self.snapshot, 0)),
+                    internal_tag: None,
+                    payload_key: Some(content),
+                },
+                _ => VariantAccess {
+                    payload: self,
+                    internal_tag: Some(tag),
+                    payload_key: None,
+                },
+            };
+            EnumAccess {
+                tag: Key::Node(variant),
+                payload,
+            }
+        } else if let Some(entries) = self.entries() {
+            let mut entries = entries;
+            match (entries.next(), entries.count()) {
+                (Some((key, payload)), 0) => EnumAccess {
+                    tag: Key::Str(key),
+                    payload: VariantAccess {
+                        payload,
+                        internal_tag: None,
+                        payload_key: Some(key),
+                    },
+                },
+                (first, rest) => {
+                    let len = first.map_or(0, |_| 1 + rest);
+                    return Err(de::Error::invalid_length(len, &"1"));
+                }
+            }
+        } else {
+            return self.invalid_type(visitor);
+        };
+        visitor.visit_enum(access)
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if let Some(bytes) = self.as_bytes() {
+            let len = bytes.len() as u32;
+            check_limit(self.config().max_bytes_length, len, "bytes length")?;
+            visitor.visit_borrowed_bytes(bytes)
+        } else if let (Kind::Array(items), true) =
+            (self.kind(), self.config().deserialize_bytes_from_arrays)
+        {
+            let len = items.len() as u32;
+            check_limit(self.config().max_bytes_length, len, "bytes length")?;
+            self.deserialize_seq(visitor)
+        } else {
+            self.invalid_type(visitor)
+        }
+    }
+
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+}
@@ -1,18 +1,170 @@
+use serde::de::{Expected, Unexpected};
+use std::fmt;
 use wasm_bindgen::prelude::*;
 
-/// A newtype that represents Serde errors as JavaScript exceptions.
+/// A single step on the way from the root value to the one that caused an [`Error`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A struct field or an object property.
+    Field(String),
+    /// An element of a sequence.
+    Index(u32),
+    /// The key of the n-th map entry.
+    MapKey(u32),
+    /// The value of the n-th map entry, if its key is not a string.
+    MapValue(u32),
+}
+
+/// Category of an [`Error`].
+///
+/// New kinds may be added in the future, so matches on it need a wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The value has a different type than expected, e.g. a string instead of a number.
+    InvalidType,
+    /// The value has the right type, but is not acceptable, e.g. an unknown enum variant
+    /// or a sequence of the wrong length.
+    InvalidValue,
+    /// A required struct field is missing.
+    MissingField,
+    /// An object has a property that doesn't correspond to any struct field.
+    UnknownField,
+    /// A number doesn't fit into the target type.
+    OutOfRange,
+    /// An exception was thrown by JavaScript code, e.g. by an iterator.
+    JsException,
+    /// An object contains itself, directly or through other objects.
+    Cycle,
+    /// Containers are nested deeper than allowed by [`DeserializerConfig::max_depth`](crate::DeserializerConfig::max_depth).
+    DepthLimit,
+    /// The input exceeds one of the size limits of [`DeserializerConfig`](crate::DeserializerConfig),
+    /// such as the maximum string length.
+    LimitExceeded,
+    /// Any other error, including custom errors raised by `Serialize` and `Deserialize` implementations.
+    Custom,
+}
+
+impl ErrorKind {
+    /// Returns the name of the kind, as exposed in the `kind` property of JavaScript errors.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::InvalidType => "InvalidType",
+            ErrorKind::InvalidValue => "InvalidValue",
+            ErrorKind::MissingField => "MissingField",
+            ErrorKind::UnknownField => "UnknownField",
+            ErrorKind::OutOfRange => "OutOfRange",
+            ErrorKind::JsException => "JsException",
+            ErrorKind::Cycle => "Cycle",
+            ErrorKind::DepthLimit => "DepthLimit",
+            ErrorKind::LimitExceeded => "LimitExceeded",
+            ErrorKind::Custom => "Custom",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug)]
+struct ErrorImpl {
+    kind: ErrorKind,
+    message: String,
+    expected: Option<String>,
+    received: Option<String>,
+    path: Vec<PathSegment>,
+    /// The original exception for [`ErrorKind::JsException`].
+    exception: Option<JsValue>,
+}
+
+/// Serde errors that are converted to JavaScript exceptions when passed to JavaScript.
+///
+/// Errors that happen inside of nested values record the path to the value,
+/// which is appended to the message and also available via [`Error::path`].
+///
+/// When converted into a [`JsValue`], errors of kind [`ErrorKind::InvalidType`] become
+/// `TypeError`s, errors of kind [`ErrorKind::OutOfRange`] become `RangeError`s and all
+/// others become plain `Error`s. The resulting object has the following properties
+/// in addition to `message`:
+///  - `kind` - name of the [`ErrorKind`].
+///  - `expected` and `received` - descriptions of the expected and the actual value, if known.
+///  - `path` - the path formatted like `order.items[3].price`, if not empty.
+///
+/// Exceptions thrown by JavaScript are passed through as is, except for the `path` property.
 #[derive(Debug)]
-pub struct Error(JsValue);
-
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        #[wasm_bindgen]
-        extern "C" {
-            #[wasm_bindgen(js_name = String)]
-            pub fn to_string(value: &JsValue) -> String;
+pub struct Error(Box<ErrorImpl>);
+
+/// Formats a path like `order.items[3].price`.
+struct DisplayPath<'a>(&'a [PathSegment]);
+
+impl fmt::Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Field(name) if is_identifier(name) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(name)?;
+                }
+                PathSegment::Field(name) => write!(f, "[{:?}]", name)?,
+                PathSegment::Index(idx) => write!(f, "[{}]", idx)?,
+                PathSegment::MapKey(idx) => write!(f, "[Map key #{}]", idx)?,
+                PathSegment::MapValue(idx) => write!(f, "[Map value #{}]", idx)?,
+            }
         }
+        Ok(())
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Formats a list of names the same way Serde does in its default error messages.
+struct OneOf(&'static [&'static str]);
+
+impl fmt::Display for OneOf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            [] => f.write_str("nothing"),
+            [a] => write!(f, "`{}`", a),
+            [a, b] => write!(f, "`{}` or `{}`", a, b),
+            names => {
+                f.write_str("one of ")?;
+                for (i, name) in names.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "`{}`", name)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
 
-        to_string(&self.0).fmt(f)
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = String)]
+    fn to_string(value: &JsValue) -> String;
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0.exception {
+            Some(exception) => to_string(exception).fmt(f)?,
+            None => self.0.message.fmt(f)?,
+        }
+        if !self.0.path.is_empty() {
+            write!(f, " at {}", DisplayPath(&self.0.path))?;
+        }
+        Ok(())
     }
 }
 
@@ -20,28 +172,162 @@ impl std::error::Error for Error {}
 
 impl Error {
     /// Creates a JavaScript `Error` with a given message.
-    pub fn new<T: std::fmt::Display>(msg: T) -> Self {
-        Error(JsError::new(&msg.to_string()).into())
+    pub fn new<T: fmt::Display>(msg: T) -> Self {
+        Self::with_kind(ErrorKind::Custom, msg)
+    }
+
+    pub(crate) fn with_kind<T: fmt::Display>(kind: ErrorKind, msg: T) -> Self {
+        Error(Box::new(ErrorImpl {
+            kind,
+            message: msg.to_string(),
+            expected: None,
+            received: None,
+            path: Vec::new(),
+            exception: None,
+        }))
+    }
+
+    pub(crate) fn with_expected(mut self, expected: impl fmt::Display) -> Self {
+        self.0.expected = Some(expected.to_string());
+        self
+    }
+
+    pub(crate) fn with_received(mut self, received: impl fmt::Display) -> Self {
+        self.0.received = Some(received.to_string());
+        self
+    }
+
+    /// Returns the category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.0.kind
+    }
+
+    /// Returns a description of the expected value, if known.
+    pub fn expected(&self) -> Option<&str> {
+        self.0.expected.as_deref()
+    }
+
+    /// Returns a description of the value that was actually found, if known.
+    pub fn received(&self) -> Option<&str> {
+        self.0.received.as_deref()
+    }
+
+    /// Returns the path to the value that caused this error, starting from the root value.
+    ///
+    /// The path is empty if the error was caused by the root value itself.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.0.path
+    }
+
+    /// Returns the original exception if this error was thrown by JavaScript.
+    pub fn js_exception(&self) -> Option<&JsValue> {
+        self.0.exception.as_ref()
+    }
+
+    /// Prepends a segment to the path as the error bubbles up to the root value.
+    pub(crate) fn at(mut self, segment: PathSegment) -> Self {
+        self.0.path.insert(0, segment);
+        self
+    }
+
+    /// Like [`serde::de::Error::invalid_value`], but always of kind [`ErrorKind::OutOfRange`],
+    /// e.g. for decimal strings that are valid integers, but too large for the target type.
+    pub(crate) fn out_of_range(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        Self::with_values(ErrorKind::OutOfRange, unexp, exp)
+    }
+
+    fn with_values(kind: ErrorKind, unexp: Unexpected, exp: &dyn Expected) -> Self {
+        Error::with_kind(
+            kind,
+            format_args!("invalid value: {}, expected {}", unexp, exp),
+        )
+        .with_expected(exp)
+        .with_received(unexp)
     }
 }
 
 impl serde::ser::Error for Error {
-    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
         Error::new(msg)
     }
 }
 
 impl serde::de::Error for Error {
-    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
         Error::new(msg)
     }
+
+    fn invalid_type(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        Error::with_kind(
+            ErrorKind::InvalidType,
+            format_args!("invalid type: {}, expected {}", unexp, exp),
+        )
+        .with_expected(exp)
+        .with_received(unexp)
+    }
+
+    /// Numbers are only rejected by Serde's own visitors if they don't fit into the target type.
+    fn invalid_value(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        let kind = match unexp {
+            Unexpected::Signed(_) | Unexpected::Unsigned(_) | Unexpected::Float(_) => {
+                ErrorKind::OutOfRange
+            }
+            _ => ErrorKind::InvalidValue,
+        };
+        Error::with_values(kind, unexp, exp)
+    }
+
+    fn invalid_length(len: usize, exp: &dyn Expected) -> Self {
+        Error::with_kind(
+            ErrorKind::InvalidValue,
+            format_args!("invalid length {}, expected {}", len, exp),
+        )
+        .with_expected(exp)
+        .with_received(format_args!("length {}", len))
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        let msg = if expected.is_empty() {
+            format!("unknown variant `{}`, there are no variants", variant)
+        } else {
+            format!(
+                "unknown variant `{}`, expected {}",
+                variant,
+                OneOf(expected)
+            )
+        };
+        Error::with_kind(ErrorKind::InvalidValue, msg)
+            .with_expected(OneOf(expected))
+            .with_received(format_args!("`{}`", variant))
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        let msg = if expected.is_empty() {
+            format!("unknown field `{}`, there are no fields", field)
+        } else {
+            format!("unknown field `{}`, expected {}", field, OneOf(expected))
+        };
+        Error::with_kind(ErrorKind::UnknownField, msg)
+            .with_expected(OneOf(expected))
+            .with_received(format_args!("`{}`", field))
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::with_kind(
+            ErrorKind::MissingField,
+            format_args!("missing field `{}`", field),
+        )
+        .with_expected(format_args!("`{}`", field))
+    }
 }
 
 /// This conversion is needed for `?` to just work when using wasm-bindgen
 /// imports that return JavaScript exceptions as `Result<T, JsValue>`.
 impl From<JsValue> for Error {
     fn from(error: JsValue) -> Error {
-        Error(error)
+        let mut err = Error::with_kind(ErrorKind::JsException, "");
+        err.0.exception = Some(error);
+        err
     }
 }
 
@@ -49,6 +335,33 @@ impl From<JsValue> for Error {
 // that return `Result<T, JsValue>` to throw JavaScript exceptions.
 impl From<Error> for JsValue {
     fn from(error: Error) -> JsValue {
-        error.0
+        fn set(target: &JsValue, key: &str, value: &str) {
+            // Frozen or otherwise exotic exceptions are passed through as is.
+            let _ = js_sys::Reflect::set(target, &key.into(), &value.into());
+        }
+
+        let value = match &error.0.exception {
+            Some(exception) => exception.clone(),
+            None => {
+                let message = error.to_string();
+                let value = match error.0.kind {
+                    ErrorKind::InvalidType => js_sys::TypeError::new(&message).into(),
+                    ErrorKind::OutOfRange => js_sys::RangeError::new(&message).into(),
+                    _ => JsValue::from(JsError::new(&message)),
+                };
+                set(&value, "kind", error.0.kind.as_str());
+                if let Some(expected) = &error.0.expected {
+                    set(&value, "expected", expected);
+                }
+                if let Some(received) = &error.0.received {
+                    set(&value, "received", received);
+                }
+                value
+            }
+        };
+        if !error.0.path.is_empty() && value.is_object() {
+            set(&value, "path", &DisplayPath(&error.0.path).to_string());
+        }
+        value
     }
 }
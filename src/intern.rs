@@ -0,0 +1,94 @@
+use js_sys::JsString;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Statistics of the string interning cache enabled via
+/// [`Serializer::intern_strings`](crate::Serializer::intern_strings).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StringCacheStats {
+    /// Number of strings found in the cache.
+    pub hits: u64,
+    /// Number of strings that had to be converted and were added to the cache.
+    pub misses: u64,
+    /// Number of strings dropped from the cache to stay within its capacity.
+    pub evictions: u64,
+    /// Number of strings currently in the cache.
+    pub len: usize,
+}
+
+/// An approximate LRU cache made of two generations.
+///
+/// New and recently used strings go into `recent`. Once it holds half of the capacity,
+/// it becomes `older` and whatever was in `older` before is dropped, so strings that
+/// weren't used during the last generation are evicted without tracking exact recency.
+#[derive(Default)]
+struct StringCache {
+    recent: HashMap<Box<str>, JsString>,
+    older: HashMap<Box<str>, JsString>,
+    stats: StringCacheStats,
+}
+
+impl StringCache {
+    fn get(&mut self, s: &str, capacity: usize) -> JsString {
+        if let Some(js) = self.recent.get(s) {
+            self.stats.hits += 1;
+            return js.clone();
+        }
+        let (key, js) = match self.older.remove_entry(s) {
+            Some(entry) => {
+                self.stats.hits += 1;
+                entry
+            }
+            None => {
+                self.stats.misses += 1;
+                (s.into(), s.into())
+            }
+        };
+        if self.recent.len() >= (capacity / 2).max(1) {
+            self.stats.evictions += self.older.len() as u64;
+            self.older = std::mem::take(&mut self.recent);
+        }
+        self.recent.insert(key, js.clone());
+        js
+    }
+
+    fn len(&self) -> usize {
+        self.recent.len() + self.older.len()
+    }
+}
+
+thread_local! {
+    // Shared between all serializers, so that strings are reused across calls,
+    // e.g. when the same keys are serialized on every frame.
+    static CACHE: RefCell<StringCache> = RefCell::default();
+}
+
+/// Strings longer than this many bytes, e.g. text content rather than keys or enum-like
+/// values, are unlikely to repeat and would only push useful strings out of the cache.
+const MAX_INTERNED_LEN: usize = 64;
+
+/// Converts a string via the interning cache, keeping at most `capacity` strings in it.
+///
+/// Long strings bypass the cache and don't count towards its statistics.
+pub(crate) fn interned_str_to_js(s: &str, capacity: usize) -> JsString {
+    if s.len() > MAX_INTERNED_LEN {
+        return s.into();
+    }
+    CACHE.with(|cache| cache.borrow_mut().get(s, capacity))
+}
+
+/// Returns statistics of the string interning cache of the current thread.
+pub fn string_cache_stats() -> StringCacheStats {
+    CACHE.with(|cache| {
+        let cache = cache.borrow();
+        StringCacheStats {
+            len: cache.len(),
+            ..cache.stats
+        }
+    })
+}
+
+/// Drops all strings from the interning cache of the current thread and resets its statistics.
+pub fn clear_string_cache() {
+    CACHE.with(|cache| *cache.borrow_mut() = StringCache::default());
+}
@@ -2,21 +2,66 @@
 #![warn(missing_docs)]
 #![warn(clippy::missing_const_for_fn)]
 
-use js_sys::JsString;
+use js_sys::{JsString, Object};
 use wasm_bindgen::prelude::*;
 
 mod de;
 mod error;
-mod preserve;
+mod intern;
+pub mod preserve;
 mod ser;
+pub mod typed_array;
 
-pub use de::Deserializer;
-pub use error::Error;
-pub use preserve::*;
-pub use ser::Serializer;
+pub use de::{Deserializer, DeserializerConfig, Merge, Patch, UnknownFieldCallback, UnknownFields};
+pub use error::{Error, ErrorKind, PathSegment};
+pub use intern::{clear_string_cache, string_cache_stats, StringCacheStats};
+pub use preserve::PreserveJsValue;
+pub use ser::{FreezeMode, Serializer};
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// Representation of 64-bit and 128-bit integers in JavaScript, used both by the
+/// [`Serializer`] and the [`Deserializer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberPolicy {
+    /// A `number` if the value is in the [safe integer] range, otherwise a `bigint`.
+    ///
+    /// [safe integer]: https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Number/isSafeInteger
+    NumberOrBigInt,
+    /// Always a `bigint`.
+    BigInt,
+    /// Always a `number`, failing for values outside of the [safe integer] range.
+    ///
+    /// [safe integer]: https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Number/isSafeInteger
+    Number,
+    /// Always a decimal `string`.
+    String,
+}
+
+/// Representation of Rust enum variants in JavaScript, used both by the [`Serializer`]
+/// and the [`Deserializer`] for enums without Serde representation attributes.
+///
+/// Unit variants are always accepted as plain `"Variant"` strings when deserializing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnumRepresentation {
+    /// `{ Variant: payload }`, compatible with `serde_json`. Unit variants are represented
+    /// as `"Variant"` strings. This is the default.
+    External,
+    /// `{ [tag]: "Variant", ...fields }`. Only supports unit variants, struct variants and
+    /// newtype variants containing structs or maps.
+    Internal {
+        /// Name of the property holding the variant name.
+        tag: &'static str,
+    },
+    /// `{ [tag]: "Variant", [content]: payload }`.
+    Adjacent {
+        /// Name of the property holding the variant name.
+        tag: &'static str,
+        /// Name of the property holding the variant payload.
+        content: &'static str,
+    },
+}
+
 fn static_str_to_js(s: &'static str) -> JsString {
     use std::cell::RefCell;
     use std::collections::HashMap;
@@ -69,6 +114,127 @@ extern "C" {
 
     #[wasm_bindgen(method, indexing_setter)]
     fn set(this: &ObjectExt, key: JsString, value: JsValue);
+
+    /// `Object.prototype.hasOwnProperty`, which works for objects without a prototype
+    /// and, unlike `Object.hasOwn`, in engines older than ES2022.
+    #[wasm_bindgen(js_namespace = ["Object", "prototype", "hasOwnProperty"], js_name = call)]
+    fn has_own_property(obj: &JsValue, key: &JsValue) -> bool;
+}
+
+thread_local! {
+    static OBJECT_PROTOTYPE: Object = Object::get_prototype_of(&Object::new());
+}
+
+/// Checks whether a value is an ordinary object inheriting directly from `Object.prototype`.
+fn inherits_object_prototype(value: &JsValue) -> bool {
+    OBJECT_PROTOTYPE.with(|proto| Object::get_prototype_of(value) == *proto)
+}
+
+/// Checks whether `Object.prototype` provides a property, e.g. a builtin like `toString`
+/// or something added by prototype pollution.
+fn object_prototype_has(key: &JsValue) -> bool {
+    OBJECT_PROTOTYPE.with(|proto| has_own_property(proto, key))
+}
+
+/// The key that `Object.prototype` turns into an accessor for the prototype itself.
+const PROTO: &str = "__proto__";
+
+impl ObjectExt {
+    /// Creates an object without a prototype, where `__proto__` is an ordinary key.
+    fn null_prototype() -> Self {
+        Object::create(JsValue::NULL.unchecked_ref::<Object>()).unchecked_into()
+    }
+
+    /// Defines an own enumerable data property, which works even for `__proto__`.
+    fn define(&self, key: &JsValue, value: JsValue) {
+        let descriptor = Object::new().unchecked_into::<ObjectExt>();
+        descriptor.set(static_str_to_js("value"), value);
+        for flag in ["writable", "enumerable", "configurable"].iter() {
+            descriptor.set(static_str_to_js(flag), JsValue::TRUE);
+        }
+        Object::define_property(
+            self.unchecked_ref::<Object>(),
+            key,
+            descriptor.unchecked_ref(),
+        );
+    }
+
+    /// Like [`ObjectExt::set`], but creates an own property instead of changing
+    /// the prototype of an ordinary object for the `__proto__` key.
+    fn set_static(&self, key: &'static str, value: JsValue) {
+        if key == PROTO {
+            self.define(&static_str_to_js(key), value);
+        } else {
+            self.set(static_str_to_js(key), value);
+        }
+    }
+
+    /// Same as [`ObjectExt::set_static`] for a key that is only known at runtime.
+    ///
+    /// Callers compare the key against `__proto__` on the Rust side before converting it,
+    /// so that JS strings never have to be compared.
+    fn set_own(&self, key: JsValue, is_proto: bool, value: JsValue) {
+        if is_proto {
+            self.define(&key, value);
+        } else {
+            self.set(key.unchecked_into(), value);
+        }
+    }
+
+    /// Reads an own property, or returns `None` if the key is missing or only inherited.
+    ///
+    /// Like for [`ObjectExt::set_own`], `is_proto` must be checked by the caller.
+    fn get_own(&self, key: &JsValue, is_proto: bool) -> Option<JsValue> {
+        let value = self.get_with_ref_key(key.unchecked_ref());
+        let maybe_inherited = value.is_undefined() || is_proto;
+        if maybe_inherited && !has_own_property(self, key) {
+            return None;
+        }
+        Some(value)
+    }
+
+    /// Reads a struct field or an enum tag, or returns `None` if the key is missing.
+    ///
+    /// Inherited data properties are ignored, so that e.g. a polluted `Object.prototype`
+    /// can't inject fields, but getters of classes and other prototypes are still read.
+    /// Ownership is only checked if the value is `undefined` or if the caller says it
+    /// `may_be_inherited`, which spares a call to JS for most fields of plain objects.
+    fn get_field(&self, key: &JsString, may_be_inherited: bool) -> Option<JsValue> {
+        let value = self.get_with_ref_key(key);
+        if !(value.is_undefined() || may_be_inherited) || has_own_property(self, key) {
+            return Some(value);
+        }
+        let is_getter = !value.is_undefined() && has_inherited_getter(self.unchecked_ref(), key);
+        is_getter.then_some(value)
+    }
+
+    /// Copies own enumerable properties of `source` like `Object.assign`, but without
+    /// calling the `__proto__` setter of an ordinary object.
+    fn assign_own(&self, source: &Object) {
+        if has_own_property(source, &static_str_to_js(PROTO)) {
+            let descriptors = Object::get_own_property_descriptors(source);
+            Object::define_properties(self.unchecked_ref::<Object>(), descriptors.unchecked_ref());
+        } else {
+            Object::assign(self.unchecked_ref::<Object>(), source);
+        }
+    }
+}
+
+/// Checks whether an inherited property is a getter of a prototype below `Object.prototype`.
+fn has_inherited_getter(obj: &Object, key: &JsValue) -> bool {
+    let object_prototype = OBJECT_PROTOTYPE.with(Object::clone);
+    let mut proto = Object::get_prototype_of(obj);
+    while !proto.is_null() && proto != object_prototype {
+        let descriptor = Object::get_own_property_descriptor(&proto, key);
+        if !descriptor.is_undefined() {
+            let descriptor = descriptor.unchecked_into::<ObjectExt>();
+            return descriptor
+                .get_with_ref_key(&static_str_to_js("get"))
+                .is_function();
+        }
+        proto = Object::get_prototype_of(&proto);
+    }
+    false
 }
 
 /// Converts [`JsValue`] into a Rust type.
@@ -76,7 +242,94 @@ pub fn from_value<T: serde::de::DeserializeOwned>(value: JsValue) -> Result<T> {
     T::deserialize(Deserializer::from(value))
 }
 
+/// Converts [`JsValue`] into a Rust type using the given [`DeserializerConfig`].
+pub fn from_value_with<T: serde::de::DeserializeOwned>(
+    value: JsValue,
+    config: &DeserializerConfig,
+) -> Result<T> {
+    T::deserialize(Deserializer::with_config(value, config))
+}
+
+/// Deserializes a [`JsValue`] into an existing Rust value, reusing its allocations where
+/// the type supports it.
+///
+/// This follows the in-place hooks of Serde: `Vec`s, `String`s, sets and tuples keep their
+/// buffers and update their elements in place, while other types, including `HashMap`s and
+/// derived structs, are deserialized anew. Derived structs update their fields in place as
+/// well if the `deserialize_in_place` feature of `serde_derive` is enabled.
+pub fn from_value_in_place<T: serde::de::DeserializeOwned>(
+    place: &mut T,
+    value: JsValue,
+) -> Result<()> {
+    de::deserialize_in_place(place, value, &DeserializerConfig::new())
+}
+
+/// Deserializes a [`JsValue`] into an existing Rust value like [`from_value_in_place`],
+/// using the given [`DeserializerConfig`].
+pub fn from_value_in_place_with<T: serde::de::DeserializeOwned>(
+    place: &mut T,
+    value: JsValue,
+    config: &DeserializerConfig,
+) -> Result<()> {
+    de::deserialize_in_place(place, value, config)
+}
+
+/// Applies a partial update from a JS object to an existing Rust value.
+///
+/// Only the fields present in `patch` are updated, recursing into nested structs and the
+/// existing entries of maps, while missing fields and entries are left untouched.
+/// Structs opt in by implementing [`Merge`] with [`Patch::merge_fields`], so fields that
+/// aren't listed there, like caches, are never touched either. Primitives, strings and
+/// `Vec`s are replaced as a whole, and an `Option` is cleared by a `null` or `undefined` patch.
+///
+/// Errors include the path to the mismatching property of the patch. Fields updated before
+/// the error keep their new values.
+pub fn merge_from_value<T: ?Sized + Merge>(place: &mut T, patch: JsValue) -> Result<()> {
+    de::merge(place, patch, &DeserializerConfig::new())
+}
+
+/// Applies a partial update like [`merge_from_value`], using the given [`DeserializerConfig`].
+pub fn merge_from_value_with<T: ?Sized + Merge>(
+    place: &mut T,
+    patch: JsValue,
+    config: &DeserializerConfig,
+) -> Result<()> {
+    de::merge(place, patch, config)
+}
+
+/// Converts [`JsValue`] into a Rust type like [`from_value_with`], but walks the value
+/// once in a bundled JS function and deserializes the resulting snapshot without further
+/// calls to JS.
+///
+/// Only own enumerable properties of objects are taken into account, and iterables
+/// are consumed in full, even if not all of their elements are needed.
+/// Size limits of the configuration are checked while deserializing the snapshot,
+/// so they don't bound the memory used by the snapshot itself.
+#[cfg(feature = "batch")]
+pub fn from_value_batched<T: serde::de::DeserializeOwned>(
+    value: JsValue,
+    config: &DeserializerConfig,
+) -> Result<T> {
+    de::deserialize_batched(&value, config)
+}
+
 /// Converts a Rust value into a [`JsValue`].
 pub fn to_value<T: serde::ser::Serialize + ?Sized>(value: &T) -> Result<JsValue> {
     value.serialize(&Serializer::new())
 }
+
+/// Serializes a Rust value onto an existing JS object, array or `Map`,
+/// see [`Serializer::serialize_into`].
+pub fn to_value_into<T: serde::ser::Serialize + ?Sized>(value: &T, target: &JsValue) -> Result<()> {
+    Serializer::new().serialize_into(value, target)
+}
+
+/// Converts a Rust value into a [`JsValue`] like [`to_value`], but reuses the objects,
+/// arrays and `Map`s of a previous result wherever they didn't change,
+/// see [`Serializer::serialize_diff`].
+pub fn to_value_diff<T: serde::ser::Serialize + ?Sized>(
+    value: &T,
+    previous: &JsValue,
+) -> Result<JsValue> {
+    Serializer::new().serialize_diff(value, previous)
+}
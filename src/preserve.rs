@@ -1,12 +1,52 @@
+//! Passing JavaScript values through serialization and deserialization untouched.
+//!
+//! Values can be wrapped into [`PreserveJsValue`], or the adapters in this module can be used
+//! directly on fields of type [`JsValue`], `js_sys` types or wasm-bindgen imported types:
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use wasm_bindgen::JsValue;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Callbacks {
+//!     #[serde(with = "serde_wasm_bindgen::preserve")]
+//!     on_change: js_sys::Function,
+//!     #[serde(with = "serde_wasm_bindgen::preserve::option")]
+//!     created_at: Option<js_sys::Date>,
+//!     #[serde(with = "serde_wasm_bindgen::preserve::vec")]
+//!     extra: Vec<JsValue>,
+//! }
+//! ```
+//!
+//! Unlike [`PreserveJsValue`], the adapters check that the value is an instance of the
+//! expected type via [`JsCast::dyn_into`] and fail with a type error otherwise.
+//!
+//! Other serializers, e.g. `serde_json`, see the structure of the JS value instead, following
+//! the same rules as `JSON.stringify`, and other deserializers rebuild a JS value from their
+//! data, so that the same types can still be used for logging, caching or sending to a server.
+//!
+//! Values are only passed through untouched when [`crate::Deserializer`] deserializes them
+//! directly. Serde buffers the input of `#[serde(flatten)]` fields and of `#[serde(untagged)]`
+//! and internally tagged enums in its own data model first, so inside of these the value is
+//! rebuilt from its structure just like with other deserializers: plain objects, arrays and
+//! primitives come out equivalent, but not identical, instances of classes such as `Date` turn
+//! into plain objects with their own enumerable properties, and functions are rejected.
+//! The adapters reject such rebuilt values with a type error instead of returning them.
+
 use serde::{
     de::{self, Visitor},
-    Deserialize, Deserializer, Serialize, Serializer,
+    ser, Deserialize, Deserializer, Serialize, Serializer,
 };
-use std::{fmt, sync::Mutex};
-use wasm_bindgen::JsValue;
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fmt;
+use wasm_bindgen::{JsCast, JsValue};
 
 /// This type is used to preserve a [`JsValue`] when serializing and deserializing.
 ///
+/// See the [module documentation](self) for limitations with flattened fields
+/// and untagged enums.
+///
 /// ```rust
 /// #[derive(Serialize, Deserialize)]
 /// struct MyStruct {
@@ -29,23 +69,303 @@ impl<T: From<JsValue> + Into<JsValue> + Clone + Default> Default for PreserveJsV
     }
 }
 
-#[derive(Clone)]
-pub(crate) struct JsValueKeeper(pub JsValue);
-unsafe impl Send for JsValueKeeper {}
-unsafe impl Sync for JsValueKeeper {}
+/// Name of the newtype struct that marks a preserved value for [`crate::Serializer`]
+/// and [`crate::Deserializer`].
+pub(crate) const PRESERVE_NAME: &str = "$serde_wasm_bindgen::PreserveJsValue";
+
+thread_local! {
+    /// Hands a preserved value over between a serializer or deserializer and [`PreserveJsValue`].
+    ///
+    /// The value is always taken back right after the call that hands it over, so nested and
+    /// reentrant conversions never observe each other's values.
+    static PRESERVED: Cell<Option<JsValue>> = const { Cell::new(None) };
+}
+
+pub(crate) fn hand_over(value: JsValue) {
+    PRESERVED.with(|slot| slot.set(Some(value)));
+}
+
+pub(crate) fn take_over() -> Option<JsValue> {
+    PRESERVED.with(Cell::take)
+}
+
+fn serialize_js_value<S: Serializer>(value: JsValue, serializer: S) -> Result<S::Ok, S::Error> {
+    hand_over(value.clone());
+    // Our serializer picks the value up without looking at the inner value,
+    // while other serializers, e.g. `serde_json`, see its structure instead.
+    let result = serializer.serialize_newtype_struct(PRESERVE_NAME, &Structure(&value));
+    take_over();
+    result
+}
+
+/// Serializes the structure of a JS value for serializers other than [`crate::Serializer`],
+/// following the same rules as `JSON.stringify`.
+struct Structure<'a>(&'a JsValue);
+
+impl Serialize for Structure<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self.0;
+        if value.is_null() || value.is_undefined() {
+            return serializer.serialize_unit();
+        }
+        if let Some(v) = value.as_bool() {
+            return serializer.serialize_bool(v);
+        }
+        if let Some(v) = value.as_f64() {
+            return if js_sys::Number::is_safe_integer(value) {
+                serializer.serialize_i64(v as i64)
+            } else {
+                serializer.serialize_f64(v)
+            };
+        }
+        if let Some(v) = value.as_string() {
+            return serializer.serialize_str(&v);
+        }
+        if value.is_bigint() {
+            if let Ok(v) = i128::try_from(value.clone()) {
+                return serializer.serialize_i128(v);
+            }
+            if let Ok(v) = u128::try_from(value.clone()) {
+                return serializer.serialize_u128(v);
+            }
+            return Err(ser::Error::custom(format_args!(
+                "bigint {} doesn't fit into 128 bits",
+                String::from(js_sys::JsString::from(value.clone()))
+            )));
+        }
+        if let Some(array) = value.dyn_ref::<js_sys::Array>() {
+            return serializer.collect_seq(array.iter().map(Owned));
+        }
+        if let Some(map) = value.dyn_ref::<js_sys::Map>() {
+            let mut entries = Vec::new();
+            map.for_each(&mut |v, k| entries.push((Owned(k), Owned(v))));
+            return serializer.collect_map(entries);
+        }
+        if value.is_object() && !value.is_function() {
+            let to_json = js_sys::Reflect::get(value, &"toJSON".into()).map_err(describe_error)?;
+            if let Some(to_json) = to_json.dyn_ref::<js_sys::Function>() {
+                let json = to_json.call0(value).map_err(describe_error)?;
+                return Structure(&json).serialize(serializer);
+            }
+            if let Some(view) = value.dyn_ref::<js_sys::Uint8Array>() {
+                return serializer.serialize_bytes(&view.to_vec());
+            }
+            let entries = js_sys::Object::entries(value.unchecked_ref());
+            return serializer.collect_map(entries.iter().map(|entry| {
+                let entry = entry.unchecked_into::<js_sys::Array>();
+                (Owned(entry.get(0)), Owned(entry.get(1)))
+            }));
+        }
+        Err(ser::Error::custom(format_args!(
+            "{} can only be serialized to JavaScript",
+            describe(value)
+        )))
+    }
+}
+
+/// Like [`Structure`], but for values obtained while walking the JS value.
+struct Owned(JsValue);
+
+impl Serialize for Owned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Structure(&self.0).serialize(serializer)
+    }
+}
+
+fn describe_error<E: ser::Error>(exception: JsValue) -> E {
+    ser::Error::custom(crate::Error::from(exception))
+}
+
+fn deserialize_js_value<'de, D: Deserializer<'de>>(deserializer: D) -> Result<JsValue, D::Error> {
+    deserializer.deserialize_newtype_struct(PRESERVE_NAME, JsValueVisitor)
+}
+
+/// Receives a preserved value from [`crate::Deserializer`], or rebuilds a JS value
+/// from the data of any other deserializer, e.g. `serde_json`.
+struct JsValueVisitor;
+
+impl<'de> Visitor<'de> for JsValueVisitor {
+    type Value = JsValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(take_over().unwrap_or(JsValue::NULL))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(JsValue::NULL)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        let safe = js_sys::Number::MIN_SAFE_INTEGER..=js_sys::Number::MAX_SAFE_INTEGER;
+        Ok(if safe.contains(&(v as f64)) {
+            (v as f64).into()
+        } else {
+            v.into()
+        })
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        match i64::try_from(v) {
+            Ok(v) => self.visit_i64(v),
+            Err(_) => Ok(v.into()),
+        }
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Self::Value, E> {
+        match i64::try_from(v) {
+            Ok(v) => self.visit_i64(v),
+            Err(_) => Ok(v.into()),
+        }
+    }
 
-pub(crate) static NEXT_PRESERVE: Mutex<Option<JsValueKeeper>> = Mutex::new(None);
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Self::Value, E> {
+        match u64::try_from(v) {
+            Ok(v) => self.visit_u64(v),
+            Err(_) => Ok(v.into()),
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(js_sys::Uint8Array::from(v).into())
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let array = js_sys::Array::new();
+        while let Some(Rebuilt(value)) = seq.next_element()? {
+            array.push(&value);
+        }
+        Ok(array.into())
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let object = js_sys::Object::new().unchecked_into::<crate::ObjectExt>();
+        while let Some((RebuiltKey(key, is_proto), Rebuilt(value))) = map.next_entry()? {
+            object.set_own(key, is_proto, value);
+        }
+        Ok(object.into())
+    }
+}
+
+/// A JS value rebuilt by [`JsValueVisitor`] inside of sequences and maps.
+struct Rebuilt(JsValue);
+
+impl<'de> Deserialize<'de> for Rebuilt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsValueVisitor).map(Rebuilt)
+    }
+}
+
+/// A map key rebuilt by [`JsValueVisitor`], with whether it's the string `__proto__`.
+struct RebuiltKey(JsValue, bool);
+
+impl<'de> Deserialize<'de> for RebuiltKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(KeyVisitor)
+    }
+}
+
+/// Checks string keys for `__proto__` before they are converted, and otherwise
+/// rebuilds keys like [`JsValueVisitor`].
+struct KeyVisitor;
+
+macro_rules! forward_to_js_value_visitor {
+    ($($name:ident($ty:ty);)*) => {
+        $(fn $name<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+            JsValueVisitor.$name(v).map(|key| RebuiltKey(key, false))
+        })*
+    };
+}
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = RebuiltKey;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map key")
+    }
+
+    forward_to_js_value_visitor! {
+        visit_bool(bool);
+        visit_i64(i64);
+        visit_u64(u64);
+        visit_i128(i128);
+        visit_u128(u128);
+        visit_f64(f64);
+        visit_bytes(&[u8]);
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(RebuiltKey(v.into(), v == crate::PROTO))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        JsValueVisitor
+            .visit_unit()
+            .map(|key| RebuiltKey(key, false))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        JsValueVisitor
+            .visit_none()
+            .map(|key| RebuiltKey(key, false))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        JsValueVisitor
+            .visit_seq(seq)
+            .map(|key| RebuiltKey(key, false))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        JsValueVisitor
+            .visit_map(map)
+            .map(|key| RebuiltKey(key, false))
+    }
+}
 
 impl<T: From<JsValue> + Into<JsValue> + Clone> Serialize for PreserveJsValue<T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        NEXT_PRESERVE
-            .lock()
-            .unwrap()
-            .replace(JsValueKeeper(self.0.clone().into()));
-        serializer.serialize_i64(0)
+        serialize_js_value(self.0.clone().into(), serializer)
     }
 }
 
@@ -54,27 +374,131 @@ impl<'de, T: From<JsValue> + Into<JsValue> + Clone> Deserialize<'de> for Preserv
     where
         D: Deserializer<'de>,
     {
-        struct JsValueVisitor<T: From<JsValue> + Into<JsValue> + Clone>(
-            std::marker::PhantomData<T>,
-        );
+        deserialize_js_value(deserializer).map(|value| PreserveJsValue(value.into()))
+    }
+}
 
-        impl<'de, T: From<JsValue> + Into<JsValue> + Clone> Visitor<'de> for JsValueVisitor<T> {
-            type Value = PreserveJsValue<T>;
+/// Serializes a [`JsValue`] or any other [`JsCast`] type as is.
+pub fn serialize<T: JsCast, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_js_value(value.as_ref().clone(), serializer)
+}
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("struct PreserveJsValue")
-            }
+/// Deserializes a [`JsValue`] or any other [`JsCast`] type as is, failing if the value
+/// is not an instance of `T`.
+pub fn deserialize<'de, T: JsCast, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    let value = deserialize_js_value(deserializer)?;
+    value.dyn_into::<T>().map_err(|value| {
+        let received = describe(&value);
+        de::Error::invalid_type(
+            de::Unexpected::Other(&received),
+            &format!("an instance of {}", type_name::<T>()).as_str(),
+        )
+    })
+}
 
-            fn visit_i64<E>(self, _value: i64) -> Result<Self::Value, E>
-            where
-                E: de::Error,
-            {
-                Ok(PreserveJsValue(
-                    NEXT_PRESERVE.lock().unwrap().take().unwrap().0.into(),
-                ))
-            }
+/// Describes the type of a JS value for error messages, e.g. `object Array` or `number`.
+fn describe(value: &JsValue) -> String {
+    let type_of = value.js_typeof().as_string().unwrap_or_default();
+    if value.is_object() {
+        let name = js_sys::Object::get_prototype_of(value).constructor().name();
+        if name.length() > 0 {
+            return format!("{} {}", type_of, name);
         }
+    }
+    type_of
+}
+
+/// Returns the name of a type without its module path.
+fn type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Borrows a value for serialization with [`serialize`].
+struct Preserved<'a, T>(&'a T);
+
+impl<T: JsCast> Serialize for Preserved<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(self.0, serializer)
+    }
+}
+
+/// Owns a value deserialized with [`deserialize`].
+struct Checked<T>(T);
+
+impl<'de, T: JsCast> Deserialize<'de> for Checked<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Checked)
+    }
+}
+
+/// Like the [`preserve`](self) module, but for `Option<T>`.
+pub mod option {
+    use super::*;
+
+    /// Serializes an optional [`JsCast`] value as is.
+    pub fn serialize<T: JsCast, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.as_ref().map(Preserved).serialize(serializer)
+    }
+
+    /// Deserializes an optional [`JsCast`] value as is, failing if the value is neither
+    /// missing nor an instance of `T`.
+    pub fn deserialize<'de, T: JsCast, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        Ok(Option::<Checked<T>>::deserialize(deserializer)?.map(|Checked(value)| value))
+    }
+}
+
+/// Like the [`preserve`](self) module, but for `Vec<T>`.
+pub mod vec {
+    use super::*;
+
+    /// Serializes a sequence of [`JsCast`] values as is.
+    pub fn serialize<T: JsCast, S: Serializer>(
+        value: &[T],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(value.iter().map(Preserved))
+    }
+
+    /// Deserializes a sequence of [`JsCast`] values as is, failing if any of them
+    /// is not an instance of `T`.
+    pub fn deserialize<'de, T: JsCast, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<T>, D::Error> {
+        Ok(Vec::<Checked<T>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|Checked(value)| value)
+            .collect())
+    }
+}
+
+/// Like the [`preserve`](self) module, but for `HashMap<String, T>`.
+pub mod map {
+    use super::*;
+    use std::collections::HashMap;
+    use std::hash::BuildHasher;
+
+    /// Serializes a map of [`JsCast`] values as is.
+    pub fn serialize<T: JsCast, H, S: Serializer>(
+        value: &HashMap<String, T, H>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(value.iter().map(|(key, value)| (key, Preserved(value))))
+    }
 
-        deserializer.deserialize_i64(JsValueVisitor(std::marker::PhantomData))
+    /// Deserializes a map of [`JsCast`] values as is, failing if any of them
+    /// is not an instance of `T`.
+    pub fn deserialize<'de, T: JsCast, H: BuildHasher + Default, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, T, H>, D::Error> {
+        Ok(HashMap::<String, Checked<T>, H>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, Checked(value))| (key, value))
+            .collect())
     }
 }
@@ -1,42 +1,88 @@
-use js_sys::{Array, JsString, Map, Number, Object, Uint8Array};
+use js_sys::{Array, JsString, Map, Number, Object, Symbol, Uint8Array};
 use serde::ser::{self, Error as _, Serialize};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
-use super::{static_str_to_js, Error, ObjectExt};
-use crate::preserve::NEXT_PRESERVE;
+use super::{
+    static_str_to_js, EnumRepresentation, Error, ErrorKind, NumberPolicy, ObjectExt, PathSegment,
+    PROTO,
+};
+use crate::intern::interned_str_to_js;
+use crate::preserve::{self, PRESERVE_NAME};
+use crate::typed_array::{self, TypedArrayBuffer};
 
 type Result<T = JsValue> = super::Result<T>;
 
+#[cfg(feature = "batch")]
+mod batch;
+mod diff;
+mod freeze;
+mod into;
+
+pub use freeze::FreezeMode;
+
 /// Wraps other serializers into an enum tagged variant form.
-/// Uses {"Variant": ...payload...} for compatibility with serde-json.
-pub struct VariantSerializer<S> {
+/// By default uses {"Variant": ...payload...} for compatibility with serde-json.
+pub struct VariantSerializer<'s, S> {
     variant: &'static str,
+    serializer: &'s Serializer,
     inner: S,
 }
 
-impl<S> VariantSerializer<S> {
-    pub const fn new(variant: &'static str, inner: S) -> Self {
-        Self { variant, inner }
+impl<'s, S> VariantSerializer<'s, S> {
+    pub const fn new(variant: &'static str, serializer: &'s Serializer, inner: S) -> Self {
+        Self {
+            variant,
+            serializer,
+            inner,
+        }
+    }
+
+    /// Records the location of the payload in errors raised while serializing it.
+    fn payload_error(&self, err: Error) -> Error {
+        payload_error(self.serializer.enum_representation, self.variant, err)
     }
 
     fn end(self, inner: impl FnOnce(S) -> Result) -> Result {
         let value = inner(self.inner)?;
-        let obj = Object::new();
-        obj.unchecked_ref::<ObjectExt>()
-            .set(static_str_to_js(self.variant), value);
-        Ok(obj.into())
+        let obj = match self.serializer.enum_representation {
+            EnumRepresentation::External => {
+                let obj = self.serializer.new_object();
+                obj.set_static(self.variant, value);
+                obj
+            }
+            EnumRepresentation::Adjacent { tag, content } => {
+                let obj = self.serializer.tagged_object(tag, self.variant);
+                obj.set_static(content, value);
+                obj
+            }
+            // The tag is already written by the inner serializer.
+            EnumRepresentation::Internal { .. } => return Ok(value),
+        };
+        Ok(self.serializer.freeze.apply(obj.into()))
+    }
+}
+
+fn payload_error(repr: EnumRepresentation, variant: &'static str, err: Error) -> Error {
+    match repr {
+        EnumRepresentation::External => err.at(PathSegment::Field(variant.to_owned())),
+        EnumRepresentation::Adjacent { content, .. } => {
+            err.at(PathSegment::Field(content.to_owned()))
+        }
+        EnumRepresentation::Internal { .. } => err,
     }
 }
 
 impl<S: ser::SerializeTupleStruct<Ok = JsValue, Error = Error>> ser::SerializeTupleVariant
-    for VariantSerializer<S>
+    for VariantSerializer<'_, S>
 {
     type Ok = JsValue;
     type Error = Error;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        self.inner.serialize_field(value)
+        self.inner
+            .serialize_field(value)
+            .map_err(|err| self.payload_error(err))
     }
 
     fn end(self) -> Result {
@@ -45,7 +91,7 @@ impl<S: ser::SerializeTupleStruct<Ok = JsValue, Error = Error>> ser::SerializeTu
 }
 
 impl<S: ser::SerializeStruct<Ok = JsValue, Error = Error>> ser::SerializeStructVariant
-    for VariantSerializer<S>
+    for VariantSerializer<'_, S>
 {
     type Ok = JsValue;
     type Error = Error;
@@ -55,7 +101,9 @@ impl<S: ser::SerializeStruct<Ok = JsValue, Error = Error>> ser::SerializeStructV
         key: &'static str,
         value: &T,
     ) -> Result<()> {
-        self.inner.serialize_field(key, value)
+        self.inner
+            .serialize_field(key, value)
+            .map_err(|err| self.payload_error(err))
     }
 
     fn end(self) -> Result {
@@ -84,13 +132,16 @@ impl ser::SerializeSeq for ArraySerializer<'_> {
     type Error = Error;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        self.target.set(self.idx, value.serialize(self.serializer)?);
+        let value = value
+            .serialize(self.serializer)
+            .map_err(|err| err.at(PathSegment::Index(self.idx)))?;
+        self.target.set(self.idx, value);
         self.idx += 1;
         Ok(())
     }
 
     fn end(self) -> Result {
-        Ok(self.target.into())
+        Ok(self.serializer.freeze.apply(self.target.into()))
     }
 }
 
@@ -128,7 +179,12 @@ pub enum MapResult {
 pub struct MapSerializer<'s> {
     serializer: &'s Serializer,
     target: MapResult,
-    next_key: Option<JsValue>,
+    /// The serialized key, and whether it's `__proto__`.
+    next_key: Option<(JsValue, bool)>,
+    idx: u32,
+    /// Whether the target object was created without a prototype, so that keys can be set
+    /// directly. Objects supplied by the caller never are, even with `null_prototype_objects`.
+    null_prototype: bool,
 }
 
 impl<'s> MapSerializer<'s> {
@@ -136,13 +192,38 @@ impl<'s> MapSerializer<'s> {
         Self {
             serializer,
             target: if as_object {
-                MapResult::Object(Object::new())
+                MapResult::Object(serializer.new_object().unchecked_into())
             } else {
                 MapResult::Map(Map::new())
             },
             next_key: None,
+            idx: 0,
+            null_prototype: as_object && serializer.null_prototype_objects,
         }
     }
+
+    /// Adds an already serialized entry to the target.
+    fn insert(&self, key: JsValue, is_proto: bool, value: JsValue) {
+        match &self.target {
+            MapResult::Map(map) => {
+                map.set(&key, &value);
+            }
+            MapResult::Object(object) => {
+                let object = object.unchecked_ref::<ObjectExt>();
+                if self.null_prototype {
+                    object.set(key.unchecked_into(), value);
+                } else {
+                    object.set_own(key, is_proto, value);
+                }
+            }
+        }
+    }
+}
+
+/// Checks whether a serialized string key is `__proto__`, only comparing strings of the
+/// same length to avoid copying every key out of JS.
+fn is_proto_key(key: &JsValue) -> bool {
+    key.unchecked_ref::<JsString>().length() == PROTO.len() as u32 && *key == PROTO
 }
 
 impl ser::SerializeMap for MapSerializer<'_> {
@@ -151,33 +232,38 @@ impl ser::SerializeMap for MapSerializer<'_> {
 
     fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
         debug_assert!(self.next_key.is_none());
-        self.next_key = Some(key.serialize(self.serializer)?);
+        let key = key
+            .serialize(self.serializer)
+            .and_then(|key| match self.target {
+                MapResult::Object(_) if !key.is_string() => Err(non_string_key_error()),
+                _ => Ok(key),
+            })
+            .map_err(|err| err.at(PathSegment::MapKey(self.idx)))?;
+        let is_proto = matches!(self.target, MapResult::Object(_)) && is_proto_key(&key);
+        self.next_key = Some((key, is_proto));
         Ok(())
     }
 
     fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        let key = self.next_key.take().unwrap_throw();
-        let value_ser = value.serialize(self.serializer)?;
-        match &self.target {
-            MapResult::Map(map) => {
-                map.set(&key, &value_ser);
-            }
-            MapResult::Object(object) => {
-                let key = key.dyn_into::<JsString>().map_err(|_| {
-                    Error::custom("Map key is not a string and cannot be an object key")
-                })?;
-                object.unchecked_ref::<ObjectExt>().set(key, value_ser);
-            }
-        }
+        let (key, is_proto) = self.next_key.take().unwrap_throw();
+        let value_ser = value.serialize(self.serializer).map_err(|err| {
+            err.at(match key.as_string() {
+                Some(key) => PathSegment::Field(key),
+                None => PathSegment::MapValue(self.idx),
+            })
+        })?;
+        self.idx += 1;
+        self.insert(key, is_proto, value_ser);
         Ok(())
     }
 
     fn end(self) -> Result {
         debug_assert!(self.next_key.is_none());
-        match self.target {
-            MapResult::Map(map) => Ok(map.into()),
-            MapResult::Object(object) => Ok(object.into()),
-        }
+        let value = match self.target {
+            MapResult::Map(map) => map.into(),
+            MapResult::Object(object) => object.into(),
+        };
+        Ok(self.serializer.freeze.apply(value))
     }
 }
 
@@ -190,7 +276,7 @@ impl<'s> ObjectSerializer<'s> {
     pub fn new(serializer: &'s Serializer) -> Self {
         Self {
             serializer,
-            target: Object::new().unchecked_into::<ObjectExt>(),
+            target: serializer.new_object(),
         }
     }
 }
@@ -204,23 +290,36 @@ impl ser::SerializeStruct for ObjectSerializer<'_> {
         key: &'static str,
         value: &T,
     ) -> Result<()> {
-        let value = value.serialize(self.serializer)?;
-        self.target.set(static_str_to_js(key), value);
+        let value = value
+            .serialize(self.serializer)
+            .map_err(|err| err.at(PathSegment::Field(key.to_owned())))?;
+        self.target.set_static(key, value);
         Ok(())
     }
 
     fn end(self) -> Result {
-        Ok(self.target.into())
+        Ok(self.serializer.freeze.apply(self.target.into()))
     }
 }
 
 /// A [`serde::Serializer`] that converts supported Rust values into a [`JsValue`].
-#[derive(Default)]
 pub struct Serializer {
     serialize_missing_as_null: bool,
     serialize_maps_as_objects: bool,
     serialize_large_number_types_as_bigints: bool,
     serialize_bytes_as_arrays: bool,
+    enum_representation: EnumRepresentation,
+    number_policy: Option<NumberPolicy>,
+    string_cache_capacity: usize,
+    null_prototype_objects: bool,
+    freeze: FreezeMode,
+    remove_stale_keys: bool,
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Serializer {
@@ -231,6 +330,12 @@ impl Serializer {
             serialize_maps_as_objects: false,
             serialize_large_number_types_as_bigints: false,
             serialize_bytes_as_arrays: false,
+            enum_representation: EnumRepresentation::External,
+            number_policy: None,
+            string_cache_capacity: 0,
+            null_prototype_objects: false,
+            freeze: FreezeMode::Mutable,
+            remove_stale_keys: false,
         }
     }
 
@@ -244,6 +349,12 @@ impl Serializer {
             serialize_maps_as_objects: true,
             serialize_large_number_types_as_bigints: false,
             serialize_bytes_as_arrays: true,
+            enum_representation: EnumRepresentation::External,
+            number_policy: None,
+            string_cache_capacity: 0,
+            null_prototype_objects: false,
+            freeze: FreezeMode::Mutable,
+            remove_stale_keys: false,
         }
     }
 
@@ -274,8 +385,186 @@ impl Serializer {
         self.serialize_bytes_as_arrays = value;
         self
     }
+
+    /// Sets the representation of enum variants. [`EnumRepresentation::External`] by default.
+    pub const fn enum_representation(mut self, value: EnumRepresentation) -> Self {
+        self.enum_representation = value;
+        self
+    }
+
+    /// Sets the representation of `u64`, `i64`, `usize`, `isize`, `u128` and `i128`.
+    ///
+    /// When not set, 64-bit numbers are serialized according to
+    /// [`serialize_large_number_types_as_bigints`](Self::serialize_large_number_types_as_bigints),
+    /// and 128-bit numbers are always serialized to `bigint`s.
+    pub const fn number_policy(mut self, value: NumberPolicy) -> Self {
+        self.number_policy = Some(value);
+        self
+    }
+
+    /// Set to a non-zero capacity to intern dynamic strings such as `String` values and map keys,
+    /// so that repeated strings are converted to JavaScript only once. `0` (disabled) by default.
+    ///
+    /// The cache is shared by all serializers on the current thread and keeps roughly the
+    /// `capacity` most recently used strings. Strings longer than 64 bytes are converted
+    /// without the cache. See [`string_cache_stats`](crate::string_cache_stats)
+    /// and [`clear_string_cache`](crate::clear_string_cache).
+    pub const fn intern_strings(mut self, capacity: usize) -> Self {
+        self.string_cache_capacity = capacity;
+        self
+    }
+
+    /// Set to `true` to create all objects with `Object.create(null)` instead of `{}`.
+    /// `false` by default.
+    ///
+    /// Such objects don't inherit anything from `Object.prototype`, so even a lookup of
+    /// a key like `toString` can only find data that was serialized. Either way,
+    /// keys such as `__proto__` are always created as own properties.
+    pub const fn null_prototype_objects(mut self, value: bool) -> Self {
+        self.null_prototype_objects = value;
+        self
+    }
+
+    /// Sets how objects, arrays and `Map`s are protected against mutation.
+    /// [`FreezeMode::Mutable`] by default.
+    pub const fn freeze(mut self, value: FreezeMode) -> Self {
+        self.freeze = value;
+        self
+    }
+
+    /// Set to `true` to remove keys, `Map` entries and array elements of the target of
+    /// [`serialize_into`](Self::serialize_into) that were not written. `false` by default.
+    pub const fn remove_stale_keys(mut self, value: bool) -> Self {
+        self.remove_stale_keys = value;
+        self
+    }
+
+    fn new_object(&self) -> ObjectExt {
+        if self.null_prototype_objects {
+            ObjectExt::null_prototype()
+        } else {
+            Object::new().unchecked_into()
+        }
+    }
+
+    /// Creates an object with a single `tag` property set to the variant name.
+    fn tagged_object(&self, tag: &'static str, variant: &'static str) -> ObjectExt {
+        let obj = self.new_object();
+        obj.set_static(tag, static_str_to_js(variant).into());
+        obj
+    }
+
+    /// Merges the tag with the fields of an internally tagged newtype variant's payload,
+    /// which only works for object-like payloads.
+    fn merge_tagged(&self, tag: &'static str, variant: &'static str, value: JsValue) -> Result {
+        let obj = self.tagged_object(tag, variant);
+        if value.is_object() && !Symbol::iterator().js_in(&value) {
+            if self.null_prototype_objects {
+                Object::assign(obj.unchecked_ref::<Object>(), value.unchecked_ref());
+            } else {
+                obj.assign_own(value.unchecked_ref());
+            }
+        } else if !value.is_undefined() && !value.is_null() {
+            return Err(non_object_internal_payload_error(variant));
+        }
+        Ok(self.freeze.apply(obj.into()))
+    }
+
+    /// Serializes a 64-bit or 128-bit integer according to the given [`NumberPolicy`].
+    ///
+    /// `safe` must contain the value as `f64` if it's in the safe integer range.
+    fn serialize_large_integer<T: Into<JsValue> + std::fmt::Display>(
+        &self,
+        policy: NumberPolicy,
+        v: T,
+        safe: Option<f64>,
+    ) -> Result {
+        Ok(match large_integer(policy, &v, safe)? {
+            LargeInteger::Number(safe) => safe.into(),
+            LargeInteger::BigInt => v.into(),
+            LargeInteger::String => v.to_string().into(),
+        })
+    }
+
+    const fn policy_for_64_bit(&self) -> NumberPolicy {
+        match self.number_policy {
+            Some(policy) => policy,
+            None if self.serialize_large_number_types_as_bigints => NumberPolicy::BigInt,
+            None => NumberPolicy::Number,
+        }
+    }
+
+    const fn policy_for_128_bit(&self) -> NumberPolicy {
+        match self.number_policy {
+            Some(policy) => policy,
+            None => NumberPolicy::BigInt,
+        }
+    }
+}
+
+/// Representation of a 64-bit or 128-bit integer chosen by a [`NumberPolicy`].
+enum LargeInteger {
+    Number(f64),
+    BigInt,
+    String,
+}
+
+fn large_integer(
+    policy: NumberPolicy,
+    v: &impl std::fmt::Display,
+    safe: Option<f64>,
+) -> Result<LargeInteger> {
+    match (policy, safe) {
+        (NumberPolicy::NumberOrBigInt, Some(safe)) | (NumberPolicy::Number, Some(safe)) => {
+            Ok(LargeInteger::Number(safe))
+        }
+        (NumberPolicy::NumberOrBigInt, None) | (NumberPolicy::BigInt, _) => {
+            Ok(LargeInteger::BigInt)
+        }
+        (NumberPolicy::Number, None) => Err(Error::with_kind(
+            ErrorKind::OutOfRange,
+            format_args!("{} can't be represented as a JavaScript number", v),
+        )
+        .with_expected("a safe integer")
+        .with_received(v)),
+        (NumberPolicy::String, _) => Ok(LargeInteger::String),
+    }
+}
+
+/// Returns the value as an object if it's neither an array nor a `Map`, i.e. it could hold
+/// the fields of a struct.
+fn plain_object(value: &JsValue) -> Option<&ObjectExt> {
+    let plain = value.is_object() && !Array::is_array(value) && !value.is_instance_of::<Map>();
+    plain.then(|| value.unchecked_ref())
+}
+
+fn non_string_key_error() -> Error {
+    Error::with_kind(
+        ErrorKind::InvalidType,
+        "Map key is not a string and cannot be an object key",
+    )
+    .with_expected("a string key")
+}
+
+fn non_object_internal_payload_error(variant: &'static str) -> Error {
+    Error::custom(format_args!(
+        "cannot serialize internally tagged newtype variant {} containing a non-object value",
+        variant
+    ))
 }
 
+fn internal_tuple_variant_error(variant: &'static str) -> Error {
+    Error::custom(format_args!(
+        "cannot serialize tuple variant {} with an internal tag",
+        variant
+    ))
+}
+
+// Note: don't try to "simplify" by using `.abs()` as it can overflow,
+// but range check can't.
+const MIN_SAFE_INTEGER: i64 = Number::MIN_SAFE_INTEGER as i64;
+const MAX_SAFE_INTEGER: i64 = Number::MAX_SAFE_INTEGER as i64;
+
 macro_rules! forward_to_into {
     ($($name:ident($ty:ty);)*) => {
         $(fn $name(self, v: $ty) -> Result {
@@ -291,10 +580,10 @@ impl<'s> ser::Serializer for &'s Serializer {
     type SerializeSeq = ArraySerializer<'s>;
     type SerializeTuple = ArraySerializer<'s>;
     type SerializeTupleStruct = ArraySerializer<'s>;
-    type SerializeTupleVariant = VariantSerializer<ArraySerializer<'s>>;
+    type SerializeTupleVariant = VariantSerializer<'s, ArraySerializer<'s>>;
     type SerializeMap = MapSerializer<'s>;
     type SerializeStruct = ObjectSerializer<'s>;
-    type SerializeStructVariant = VariantSerializer<ObjectSerializer<'s>>;
+    type SerializeStructVariant = VariantSerializer<'s, ObjectSerializer<'s>>;
 
     forward_to_into! {
         serialize_bool(bool);
@@ -309,55 +598,37 @@ impl<'s> ser::Serializer for &'s Serializer {
 
         serialize_f32(f32);
         serialize_f64(f64);
+    }
 
-        serialize_str(&str);
+    fn serialize_str(self, v: &str) -> Result {
+        Ok(match self.string_cache_capacity {
+            0 => v.into(),
+            capacity => interned_str_to_js(v, capacity).into(),
+        })
     }
 
     fn serialize_i64(self, v: i64) -> Result {
-        if let Some(value) = NEXT_PRESERVE.lock().unwrap().take() {
-            return Ok(value.0);
-        }
-
-        if self.serialize_large_number_types_as_bigints {
-            return Ok(v.into());
-        }
-
-        // Note: don't try to "simplify" by using `.abs()` as it can overflow,
-        // but range check can't.
-        const MIN_SAFE_INTEGER: i64 = Number::MIN_SAFE_INTEGER as i64;
-        const MAX_SAFE_INTEGER: i64 = Number::MAX_SAFE_INTEGER as i64;
-
-        if (MIN_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&v) {
-            self.serialize_f64(v as _)
-        } else {
-            Err(Error::custom(format_args!(
-                "{} can't be represented as a JavaScript number",
-                v
-            )))
-        }
+        let safe = (MIN_SAFE_INTEGER..=MAX_SAFE_INTEGER)
+            .contains(&v)
+            .then_some(v as f64);
+        self.serialize_large_integer(self.policy_for_64_bit(), v, safe)
     }
 
     fn serialize_u64(self, v: u64) -> Result {
-        if self.serialize_large_number_types_as_bigints {
-            return Ok(v.into());
-        }
-
-        if v <= Number::MAX_SAFE_INTEGER as u64 {
-            self.serialize_f64(v as _)
-        } else {
-            Err(Error::custom(format_args!(
-                "{} can't be represented as a JavaScript number",
-                v
-            )))
-        }
+        let safe = (v <= MAX_SAFE_INTEGER as u64).then_some(v as f64);
+        self.serialize_large_integer(self.policy_for_64_bit(), v, safe)
     }
 
     fn serialize_i128(self, v: i128) -> Result {
-        Ok(JsValue::from(v))
+        let safe = (MIN_SAFE_INTEGER as i128..=MAX_SAFE_INTEGER as i128)
+            .contains(&v)
+            .then_some(v as f64);
+        self.serialize_large_integer(self.policy_for_128_bit(), v, safe)
     }
 
     fn serialize_u128(self, v: u128) -> Result {
-        Ok(JsValue::from(v))
+        let safe = (v <= MAX_SAFE_INTEGER as u128).then_some(v as f64);
+        self.serialize_large_integer(self.policy_for_128_bit(), v, safe)
     }
 
     fn serialize_char(self, v: char) -> Result {
@@ -371,7 +642,7 @@ impl<'s> ser::Serializer for &'s Serializer {
         // backing memory, which will invalidate existing views (including `Uint8Array`).
         let view = unsafe { Uint8Array::view(v) };
         if self.serialize_bytes_as_arrays {
-            Ok(JsValue::from(Array::from(view.as_ref())))
+            Ok(self.freeze.apply(Array::from(view.as_ref()).into()))
         } else {
             Ok(JsValue::from(Uint8Array::new(view.as_ref())))
         }
@@ -397,21 +668,41 @@ impl<'s> ser::Serializer for &'s Serializer {
         self.serialize_unit()
     }
 
-    /// For compatibility with serde-json, serialises unit variants as "Variant" strings.
+    /// For compatibility with serde-json, serialises unit variants as "Variant" strings
+    /// unless configured to use tagged enum representation.
     fn serialize_unit_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
     ) -> Result {
-        Ok(static_str_to_js(variant).into())
+        match self.enum_representation {
+            EnumRepresentation::External => Ok(static_str_to_js(variant).into()),
+            EnumRepresentation::Internal { tag } | EnumRepresentation::Adjacent { tag, .. } => {
+                Ok(self.freeze.apply(self.tagged_object(tag, variant).into()))
+            }
+        }
     }
 
     fn serialize_newtype_struct<T: ?Sized + Serialize>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result {
+        if name == PRESERVE_NAME {
+            if let Some(value) = preserve::take_over() {
+                return Ok(value);
+            }
+        }
+        // Numeric sequences wrapped by the `typed_array` adapters are copied into a typed
+        // array straight from their slice, or collected on the Rust side first otherwise.
+        if let Some(mut buffer) = TypedArrayBuffer::for_name(name) {
+            if let Some(array) = typed_array::take_serialized(name) {
+                return Ok(array);
+            }
+            value.serialize(&mut buffer)?;
+            return Ok(buffer.into_js());
+        }
         value.serialize(self)
     }
 
@@ -422,7 +713,13 @@ impl<'s> ser::Serializer for &'s Serializer {
         variant: &'static str,
         value: &T,
     ) -> Result {
-        VariantSerializer::new(variant, self.serialize_newtype_struct(variant, value)?).end(Ok)
+        let value = self
+            .serialize_newtype_struct(variant, value)
+            .map_err(|err| payload_error(self.enum_representation, variant, err))?;
+        if let EnumRepresentation::Internal { tag } = self.enum_representation {
+            return self.merge_tagged(tag, variant, value);
+        }
+        VariantSerializer::new(variant, self, value).end(Ok)
     }
 
     /// Serialises any Rust iterable into a JS Array.
@@ -450,8 +747,12 @@ impl<'s> ser::Serializer for &'s Serializer {
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
+        if let EnumRepresentation::Internal { .. } = self.enum_representation {
+            return Err(internal_tuple_variant_error(variant));
+        }
         Ok(VariantSerializer::new(
             variant,
+            self,
             self.serialize_tuple_struct(variant, len)?,
         ))
     }
@@ -473,9 +774,13 @@ impl<'s> ser::Serializer for &'s Serializer {
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Ok(VariantSerializer::new(
-            variant,
-            self.serialize_struct(variant, len)?,
-        ))
+        let inner = match self.enum_representation {
+            EnumRepresentation::Internal { tag } => ObjectSerializer {
+                serializer: self,
+                target: self.tagged_object(tag, variant),
+            },
+            _ => self.serialize_struct(variant, len)?,
+        };
+        Ok(VariantSerializer::new(variant, self, inner))
     }
 }
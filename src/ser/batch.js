@@ -0,0 +1,152 @@
+// Builds a JavaScript value from the instruction buffer produced by `src/ser/batch.rs`.
+//
+// The instructions describe the value in post-order, so containers simply collect
+// the last few values from the stack. Opcodes must be kept in sync with `Op`.
+
+const decoder = new TextDecoder('utf-8', { ignoreBOM: true });
+
+// Defines keys like `__proto__` as own properties instead of calling the inherited setter.
+const define = (obj, key, value) =>
+  Object.defineProperty(obj, key, { value, writable: true, enumerable: true, configurable: true });
+
+// Same message as the proxy handlers in `src/ser/freeze.rs`.
+const reject = () => {
+  throw new TypeError('Cannot modify a value serialized with FreezeMode::Throw');
+};
+
+const objectHandler = {
+  set: reject,
+  deleteProperty: reject,
+  defineProperty: reject,
+  setPrototypeOf: reject,
+};
+
+// `Map` methods only work with the `Map` itself as `this`, not with its proxy.
+const mapHandler = {
+  ...objectHandler,
+  get(target, key) {
+    if (key === 'set' || key === 'delete' || key === 'clear') {
+      return reject;
+    }
+    const value = Reflect.get(target, key, target);
+    return typeof value === 'function' ? value.bind(target) : value;
+  },
+};
+
+// `freeze` is 0 for mutable values, 1 for frozen values and 2 for frozen values wrapped in
+// proxies, see `FreezeMode`.
+export function decode(ops, numbers, data, externals, nullPrototype, freeze) {
+  const stack = [];
+  const keys = [];
+  let num = 0;
+  let pos = 0;
+  let ext = 0;
+
+  const str = len => decoder.decode(data.subarray(pos, (pos += len)));
+  const take = n => stack.splice(stack.length - n, n);
+  const protect = value => {
+    if (freeze === 0) {
+      return value;
+    }
+    Object.freeze(value);
+    return freeze === 2 ? new Proxy(value, value instanceof Map ? mapHandler : objectHandler) : value;
+  };
+  const assign = (target, source) => {
+    if (!nullPrototype && Object.hasOwn(source, '__proto__')) {
+      Object.defineProperties(target, Object.getOwnPropertyDescriptors(source));
+    } else {
+      Object.assign(target, source);
+    }
+  };
+
+  for (let i = 0; i < ops.length; ) {
+    switch (ops[i++]) {
+      case 0:
+        stack.push(undefined);
+        break;
+      case 1:
+        stack.push(null);
+        break;
+      case 2:
+        stack.push(true);
+        break;
+      case 3:
+        stack.push(false);
+        break;
+      case 4:
+        stack.push(numbers[num++]);
+        break;
+      case 5:
+        stack.push(str(ops[i++]));
+        break;
+      case 6: {
+        const key = str(ops[i++]);
+        keys.push(key);
+        stack.push(key);
+        break;
+      }
+      case 7:
+        stack.push(keys[ops[i++]]);
+        break;
+      case 8:
+        stack.push(BigInt(str(ops[i++])));
+        break;
+      case 9: {
+        const len = ops[i++];
+        stack.push(data.slice(pos, (pos += len)));
+        break;
+      }
+      case 10: {
+        const len = ops[i++];
+        stack.push(protect(Array.from(data.subarray(pos, (pos += len)))));
+        break;
+      }
+      case 11:
+        stack.push(externals[ext++]);
+        break;
+      case 12:
+        stack.push(protect(take(ops[i++])));
+        break;
+      case 13: {
+        const entries = take(ops[i++] * 2);
+        const obj = nullPrototype ? Object.create(null) : {};
+        for (let j = 0; j < entries.length; j += 2) {
+          if (entries[j] === '__proto__' && !nullPrototype) {
+            define(obj, entries[j], entries[j + 1]);
+          } else {
+            obj[entries[j]] = entries[j + 1];
+          }
+        }
+        stack.push(protect(obj));
+        break;
+      }
+      case 14: {
+        const entries = take(ops[i++] * 2);
+        const map = new Map();
+        for (let j = 0; j < entries.length; j += 2) {
+          map.set(entries[j], entries[j + 1]);
+        }
+        stack.push(protect(map));
+        break;
+      }
+      case 15: {
+        const payload = stack.pop();
+        if (payload == null) {
+          break;
+        }
+        if (freeze === 0) {
+          assign(stack[stack.length - 1], payload);
+          break;
+        }
+        // The tagged object is already frozen, so merge both into a new one.
+        const obj = nullPrototype ? Object.create(null) : {};
+        assign(obj, stack.pop());
+        assign(obj, payload);
+        stack.push(protect(obj));
+        break;
+      }
+    }
+  }
+
+  return stack[0];
+}
@@ -0,0 +1,653 @@
+//! Batched serialization backend.
+//!
+//! Instead of creating JS values one by one, the whole value is encoded into instruction,
+//! number and byte buffers in linear memory, and then built by a bundled JS decoder in a
+//! single call, see `batch.js`.
+
+use super::*;
+use std::collections::HashMap;
+
+#[wasm_bindgen(module = "/src/ser/batch.js")]
+extern "C" {
+    fn decode(
+        ops: &[u32],
+        numbers: &[f64],
+        data: &[u8],
+        externals: &JsValue,
+        null_prototype: bool,
+        freeze: u32,
+    ) -> JsValue;
+}
+
+/// Instructions understood by the JS decoder.
+#[derive(Clone, Copy)]
+enum Op {
+    Undefined = 0,
+    Null = 1,
+    True = 2,
+    False = 3,
+    /// Pushes the next value from the number buffer.
+    Number = 4,
+    /// Pushes a string of the given length from the byte buffer.
+    String = 5,
+    /// Same as `String`, but also remembers the string for `KeyRef`.
+    KeyDef = 6,
+    /// Pushes the n-th string remembered by `KeyDef`.
+    KeyRef = 7,
+    /// Pushes a `bigint` parsed from a decimal string of the given length.
+    BigInt = 8,
+    /// Pushes a `Uint8Array` with the given number of bytes from the byte buffer.
+    Bytes = 9,
+    /// Same as `Bytes`, but pushes a plain array.
+    BytesArray = 10,
+    /// Pushes the next value from the externals array.
+    External = 11,
+    /// Collects the given number of values into an array.
+    Array = 12,
+    /// Collects the given number of key-value pairs into a plain object.
+    Object = 13,
+    /// Collects the given number of key-value pairs into a `Map`.
+    Map = 14,
+    /// Copies the properties of the top value into the object below it, or merges both
+    /// into a new object if values are frozen.
+    Assign = 15,
+}
+
+/// Where to find the text of an encoded string, if it's needed for an error path.
+enum StrSource {
+    Data(usize, usize),
+    Static(&'static str),
+    External(JsValue),
+}
+
+/// What kind of value was encoded, as far as the checks done by the [`Serializer`] care.
+enum Node {
+    /// `undefined` or `null`.
+    Missing,
+    String(StrSource),
+    /// Objects that can be merged with an internal tag.
+    Object,
+    Other,
+}
+
+struct Encoder<'s> {
+    serializer: &'s Serializer,
+    ops: Vec<u32>,
+    numbers: Vec<f64>,
+    data: Vec<u8>,
+    externals: Option<Array>,
+    /// Static strings such as field names are decoded only once per call.
+    keys: HashMap<*const str, u32>,
+}
+
+impl<'s> Encoder<'s> {
+    fn new(serializer: &'s Serializer) -> Self {
+        Self {
+            serializer,
+            ops: Vec::new(),
+            numbers: Vec::new(),
+            data: Vec::new(),
+            externals: None,
+            keys: HashMap::new(),
+        }
+    }
+
+    fn op(&mut self, op: Op) {
+        self.ops.push(op as u32);
+    }
+
+    fn op_with_len(&mut self, op: Op, len: usize) {
+        self.ops.extend_from_slice(&[op as u32, len as u32]);
+    }
+
+    fn bytes(&mut self, op: Op, bytes: &[u8]) -> usize {
+        let start = self.data.len();
+        self.data.extend_from_slice(bytes);
+        self.op_with_len(op, bytes.len());
+        start
+    }
+
+    fn number(&mut self, v: f64) -> Node {
+        self.numbers.push(v);
+        self.op(Op::Number);
+        Node::Other
+    }
+
+    fn str(&mut self, v: &str) -> Node {
+        let start = self.bytes(Op::String, v.as_bytes());
+        Node::String(StrSource::Data(start, v.len()))
+    }
+
+    fn static_str(&mut self, v: &'static str) -> Node {
+        let next_id = self.keys.len() as u32;
+        match *self.keys.entry(v as *const str).or_insert(next_id) {
+            id if id == next_id => {
+                self.bytes(Op::KeyDef, v.as_bytes());
+            }
+            id => self.op_with_len(Op::KeyRef, id as usize),
+        }
+        Node::String(StrSource::Static(v))
+    }
+
+    fn tagged_object(&mut self, tag: &'static str, variant: &'static str) {
+        self.static_str(tag);
+        self.static_str(variant);
+        self.op_with_len(Op::Object, 1);
+    }
+
+    fn external(&mut self, value: JsValue) -> Node {
+        let node = if value.is_undefined() || value.is_null() {
+            Node::Missing
+        } else if value.is_string() {
+            Node::String(StrSource::External(value.clone()))
+        } else if value.is_object() && !Symbol::iterator().js_in(&value) {
+            Node::Object
+        } else {
+            Node::Other
+        };
+        self.externals.get_or_insert_with(Array::new).push(&value);
+        self.op(Op::External);
+        node
+    }
+
+    fn large_integer<T: std::fmt::Display>(
+        &mut self,
+        policy: NumberPolicy,
+        v: T,
+        safe: Option<f64>,
+    ) -> Result<Node> {
+        Ok(match large_integer(policy, &v, safe)? {
+            LargeInteger::Number(safe) => self.number(safe),
+            LargeInteger::BigInt => {
+                self.bytes(Op::BigInt, v.to_string().as_bytes());
+                Node::Other
+            }
+            LargeInteger::String => self.str(&v.to_string()),
+        })
+    }
+
+    /// Returns the text of an encoded string.
+    fn text(&self, source: &StrSource) -> Option<String> {
+        match source {
+            StrSource::Data(start, len) => std::str::from_utf8(&self.data[*start..*start + *len])
+                .ok()
+                .map(str::to_owned),
+            StrSource::Static(s) => Some((*s).to_owned()),
+            StrSource::External(value) => value.as_string(),
+        }
+    }
+
+    fn finish(self) -> JsValue {
+        let externals = self.externals.map_or(JsValue::UNDEFINED, JsValue::from);
+        decode(
+            &self.ops,
+            &self.numbers,
+            &self.data,
+            &externals,
+            self.serializer.null_prototype_objects,
+            match self.serializer.freeze {
+                FreezeMode::Mutable => 0,
+                mode if mode.uses_proxies() => 2,
+                _ => 1,
+            },
+        )
+    }
+}
+
+impl Serializer {
+    /// Serializes a value like [`serde::Serialize::serialize`] with this serializer, but encodes
+    /// it into a buffer in linear memory first and builds the JS value in a single call to
+    /// a bundled JS decoder.
+    ///
+    /// This avoids crossing the JS/Wasm boundary for every node, which is usually faster for
+    /// large values with many small objects. All options except
+    /// [`intern_strings`](Self::intern_strings) are supported.
+    pub fn serialize_batched<T: ?Sized + Serialize>(&self, value: &T) -> Result {
+        let mut encoder = Encoder::new(self);
+        value.serialize(&mut encoder)?;
+        Ok(encoder.finish())
+    }
+}
+
+/// Encodes sequences, maps, structs and their variant forms.
+struct Compound<'a, 's> {
+    encoder: &'a mut Encoder<'s>,
+    container: Op,
+    len: usize,
+    next_key: Option<Node>,
+    variant: Option<&'static str>,
+}
+
+impl<'a, 's> Compound<'a, 's> {
+    const fn new(encoder: &'a mut Encoder<'s>, container: Op) -> Self {
+        Self {
+            encoder,
+            container,
+            len: 0,
+            next_key: None,
+            variant: None,
+        }
+    }
+
+    /// Starts a tuple or struct variant. The tag of internally tagged struct variants
+    /// is written as the first field.
+    fn variant(encoder: &'a mut Encoder<'s>, container: Op, variant: &'static str) -> Self {
+        let mut len = 0;
+        match encoder.serializer.enum_representation {
+            EnumRepresentation::External => {
+                encoder.static_str(variant);
+            }
+            EnumRepresentation::Adjacent { tag, content } => {
+                encoder.static_str(tag);
+                encoder.static_str(variant);
+                encoder.static_str(content);
+            }
+            EnumRepresentation::Internal { tag } => {
+                encoder.static_str(tag);
+                encoder.static_str(variant);
+                len = 1;
+            }
+        }
+        Self {
+            len,
+            variant: Some(variant),
+            ..Self::new(encoder, container)
+        }
+    }
+
+    fn payload_error(&self, err: Error) -> Error {
+        match self.variant {
+            Some(variant) => {
+                payload_error(self.encoder.serializer.enum_representation, variant, err)
+            }
+            None => err,
+        }
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value
+            .serialize(&mut *self.encoder)
+            .map_err(|err| self.payload_error(err.at(PathSegment::Index(self.len as u32))))?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.encoder.static_str(key);
+        value
+            .serialize(&mut *self.encoder)
+            .map_err(|err| self.payload_error(err.at(PathSegment::Field(key.to_owned()))))?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        self.encoder.op_with_len(self.container, self.len);
+        let node = match self.container {
+            Op::Object => Node::Object,
+            _ => Node::Other,
+        };
+        Ok(match self.variant {
+            None => node,
+            Some(_) => match self.encoder.serializer.enum_representation {
+                EnumRepresentation::External => {
+                    self.encoder.op_with_len(Op::Object, 1);
+                    Node::Object
+                }
+                EnumRepresentation::Adjacent { .. } => {
+                    self.encoder.op_with_len(Op::Object, 2);
+                    Node::Object
+                }
+                EnumRepresentation::Internal { .. } => node,
+            },
+        })
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_, '_> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Node> {
+        self.end()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_, '_> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Node> {
+        self.end()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_, '_> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Node> {
+        self.end()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_, '_> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Node> {
+        self.end()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_, '_> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Node> {
+        self.end()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_, '_> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Node> {
+        self.end()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_, '_> {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        debug_assert!(self.next_key.is_none());
+        let key = key
+            .serialize(&mut *self.encoder)
+            .and_then(|key| match (self.container, &key) {
+                (Op::Object, Node::String(_)) | (Op::Map, _) => Ok(key),
+                _ => Err(non_string_key_error()),
+            })
+            .map_err(|err| err.at(PathSegment::MapKey(self.len as u32)))?;
+        self.next_key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self.next_key.take().unwrap_throw();
+        value.serialize(&mut *self.encoder).map_err(|err| {
+            let key = match &key {
+                Node::String(source) => self.encoder.text(source),
+                _ => None,
+            };
+            err.at(match key {
+                Some(key) => PathSegment::Field(key),
+                None => PathSegment::MapValue(self.len as u32),
+            })
+        })?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        debug_assert!(self.next_key.is_none());
+        self.end()
+    }
+}
+
+macro_rules! forward_to_number {
+    ($($name:ident($ty:ty);)*) => {
+        $(fn $name(self, v: $ty) -> Result<Node> {
+            Ok(self.number(v.into()))
+        })*
+    };
+}
+
+impl<'a, 's> ser::Serializer for &'a mut Encoder<'s> {
+    type Ok = Node;
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a, 's>;
+    type SerializeTuple = Compound<'a, 's>;
+    type SerializeTupleStruct = Compound<'a, 's>;
+    type SerializeTupleVariant = Compound<'a, 's>;
+    type SerializeMap = Compound<'a, 's>;
+    type SerializeStruct = Compound<'a, 's>;
+    type SerializeStructVariant = Compound<'a, 's>;
+
+    forward_to_number! {
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+
+        serialize_f32(f32);
+        serialize_f64(f64);
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Node> {
+        self.op(if v { Op::True } else { Op::False });
+        Ok(Node::Other)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node> {
+        Ok(self.str(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node> {
+        let safe = (MIN_SAFE_INTEGER..=MAX_SAFE_INTEGER)
+            .contains(&v)
+            .then_some(v as f64);
+        self.large_integer(self.serializer.policy_for_64_bit(), v, safe)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node> {
+        let safe = (v <= MAX_SAFE_INTEGER as u64).then_some(v as f64);
+        self.large_integer(self.serializer.policy_for_64_bit(), v, safe)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Node> {
+        let safe = (MIN_SAFE_INTEGER as i128..=MAX_SAFE_INTEGER as i128)
+            .contains(&v)
+            .then_some(v as f64);
+        self.large_integer(self.serializer.policy_for_128_bit(), v, safe)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Node> {
+        let safe = (v <= MAX_SAFE_INTEGER as u128).then_some(v as f64);
+        self.large_integer(self.serializer.policy_for_128_bit(), v, safe)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node> {
+        Ok(self.str(v.encode_utf8(&mut [0; 4])))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Node> {
+        let op = if self.serializer.serialize_bytes_as_arrays {
+            Op::BytesArray
+        } else {
+            Op::Bytes
+        };
+        self.bytes(op, v);
+        Ok(Node::Other)
+    }
+
+    fn serialize_none(self) -> Result<Node> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Node> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node> {
+        self.op(if self.serializer.serialize_missing_as_null {
+            Op::Null
+        } else {
+            Op::Undefined
+        });
+        Ok(Node::Missing)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Node> {
+        match self.serializer.enum_representation {
+            EnumRepresentation::External => Ok(self.static_str(variant)),
+            EnumRepresentation::Internal { tag } | EnumRepresentation::Adjacent { tag, .. } => {
+                self.tagged_object(tag, variant);
+                Ok(Node::Object)
+            }
+        }
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Node> {
+        if name == PRESERVE_NAME {
+            if let Some(value) = preserve::take_over() {
+                return Ok(self.external(value));
+            }
+        }
+        if let Some(mut buffer) = TypedArrayBuffer::for_name(name) {
+            if let Some(array) = typed_array::take_serialized(name) {
+                return Ok(self.external(array));
+            }
+            value.serialize(&mut buffer)?;
+            return Ok(self.external(buffer.into_js()));
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node> {
+        let repr = self.serializer.enum_representation;
+        match repr {
+            EnumRepresentation::External => {
+                self.static_str(variant);
+            }
+            EnumRepresentation::Adjacent { tag, content } => {
+                self.static_str(tag);
+                self.static_str(variant);
+                self.static_str(content);
+            }
+            EnumRepresentation::Internal { tag } => self.tagged_object(tag, variant),
+        }
+        let payload = self
+            .serialize_newtype_struct(variant, value)
+            .map_err(|err| payload_error(repr, variant, err))?;
+        match repr {
+            EnumRepresentation::External => self.op_with_len(Op::Object, 1),
+            EnumRepresentation::Adjacent { .. } => self.op_with_len(Op::Object, 2),
+            // Merge the tag with the fields of the payload, which only works for object-like payloads.
+            EnumRepresentation::Internal { .. } => match payload {
+                Node::Missing | Node::Object => self.op(Op::Assign),
+                _ => return Err(non_object_internal_payload_error(variant)),
+            },
+        }
+        Ok(Node::Object)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(Compound::new(self, Op::Array))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        if let EnumRepresentation::Internal { .. } = self.serializer.enum_representation {
+            return Err(internal_tuple_variant_error(variant));
+        }
+        Ok(Compound::variant(self, Op::Array, variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        let container = if self.serializer.serialize_maps_as_objects {
+            Op::Object
+        } else {
+            Op::Map
+        };
+        Ok(Compound::new(self, container))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(Compound::new(self, Op::Object))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(Compound::variant(self, Op::Object, variant))
+    }
+}
@@ -0,0 +1,591 @@
+//! Serialization against a previous result, see [`Serializer::serialize_diff`].
+
+use super::*;
+
+/// Tag and variant name of an internally tagged variant, whose fields share an object with the tag.
+type Tag = Option<(&'static str, &'static str)>;
+
+impl Serializer {
+    /// Serializes a value like [`serde::Serialize::serialize`] with this serializer, but walks
+    /// the value alongside `previous`, a value produced earlier by the same serializer, and
+    /// returns the objects, arrays and `Map`s of `previous` wherever their contents didn't change.
+    ///
+    /// New nodes are only created on the paths to changed values, so JS code can rely on
+    /// reference equality of unchanged parts, e.g. for memoization. Entries of objects and
+    /// `Map`s are compared regardless of their order. Values passed through with
+    /// [`PreserveJsValue`](crate::PreserveJsValue) and typed arrays created by the
+    /// [`typed_array`](crate::typed_array) adapters are always taken from the new value.
+    pub fn serialize_diff<T: ?Sized + Serialize>(&self, value: &T, previous: &JsValue) -> Result {
+        value.serialize(DiffSerializer::new(self, previous.clone()))
+    }
+}
+
+/// Checks that a previous object has `len` keys besides the expected tag, if any.
+// `Option::is_none_or` would need Rust 1.82.
+#[allow(clippy::unnecessary_map_or)]
+fn has_shape(object: &ObjectExt, len: usize, tag: Tag) -> bool {
+    let tag_matches = tag.map_or(true, |(tag, variant)| {
+        object.get_own(&static_str_to_js(tag).into(), tag == PROTO)
+            == Some(static_str_to_js(variant).into())
+    });
+    let keys = Object::keys(object.unchecked_ref::<Object>()).length() as usize;
+    tag_matches && keys == len + usize::from(tag.is_some())
+}
+
+fn same_bytes(previous: &JsValue, v: &[u8], as_array: bool) -> bool {
+    if as_array {
+        if !Array::is_array(previous) {
+            return false;
+        }
+        let array = previous.unchecked_ref::<Array>();
+        array.length() as usize == v.len()
+            && (0..)
+                .zip(v)
+                .all(|(i, b)| array.get(i).as_f64() == Some(f64::from(*b)))
+    } else {
+        previous
+            .dyn_ref::<Uint8Array>()
+            .is_some_and(|array| array.length() as usize == v.len() && array.to_vec() == v)
+    }
+}
+
+/// Serializes a child value against its previous counterpart, if there was one,
+/// and records whether the result differs from it.
+#[allow(clippy::unnecessary_map_or)]
+fn diff_child<T: ?Sized + Serialize>(
+    serializer: &Serializer,
+    previous: Option<JsValue>,
+    value: &T,
+    changed: &mut bool,
+) -> Result {
+    let value = value.serialize(DiffSerializer::new(
+        serializer,
+        previous.clone().unwrap_or_default(),
+    ))?;
+    *changed |= previous.map_or(true, |previous| !Object::is(&value, &previous));
+    Ok(value)
+}
+
+struct DiffSerializer<'s> {
+    serializer: &'s Serializer,
+    previous: JsValue,
+    tag: Tag,
+}
+
+impl<'s> DiffSerializer<'s> {
+    const fn new(serializer: &'s Serializer, previous: JsValue) -> Self {
+        Self {
+            serializer,
+            previous,
+            tag: None,
+        }
+    }
+
+    /// Returns the payload of the previous value if it's the same variant of an externally
+    /// or adjacently tagged enum.
+    fn previous_payload(&self, variant: &'static str) -> Option<JsValue> {
+        let previous = plain_object(&self.previous)?;
+        let (key, tag) = match self.serializer.enum_representation {
+            EnumRepresentation::External => (variant, None),
+            EnumRepresentation::Adjacent { tag, content } => (content, Some((tag, variant))),
+            EnumRepresentation::Internal { .. } => return None,
+        };
+        if !has_shape(previous, 1, tag) {
+            return None;
+        }
+        previous.get_own(&static_str_to_js(key).into(), key == PROTO)
+    }
+}
+
+macro_rules! forward_to_serializer {
+    ($($name:ident($ty:ty);)*) => {
+        $(fn $name(self, v: $ty) -> Result {
+            ser::Serializer::$name(self.serializer, v)
+        })*
+    };
+}
+
+impl<'s> ser::Serializer for DiffSerializer<'s> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    type SerializeSeq = DiffArray<'s>;
+    type SerializeTuple = DiffArray<'s>;
+    type SerializeTupleStruct = DiffArray<'s>;
+    type SerializeTupleVariant = DiffVariant<'s, DiffArray<'s>>;
+    type SerializeMap = DiffMap<'s>;
+    type SerializeStruct = DiffObject<'s>;
+    type SerializeStructVariant = DiffVariant<'s, DiffObject<'s>>;
+
+    forward_to_serializer! {
+        serialize_bool(bool);
+
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_i128(i128);
+
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_u128(u128);
+
+        serialize_f32(f32);
+        serialize_f64(f64);
+
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_unit_struct(&'static str);
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result {
+        if same_bytes(&self.previous, v, self.serializer.serialize_bytes_as_arrays) {
+            return Ok(self.previous);
+        }
+        ser::Serializer::serialize_bytes(self.serializer, v)
+    }
+
+    fn serialize_none(self) -> Result {
+        ser::Serializer::serialize_none(self.serializer)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result {
+        ser::Serializer::serialize_unit(self.serializer)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result {
+        if let EnumRepresentation::Internal { tag } | EnumRepresentation::Adjacent { tag, .. } =
+            self.serializer.enum_representation
+        {
+            if plain_object(&self.previous)
+                .is_some_and(|prev| has_shape(prev, 0, Some((tag, variant))))
+            {
+                return Ok(self.previous);
+            }
+        }
+        ser::Serializer::serialize_unit_variant(self.serializer, name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result {
+        if name == PRESERVE_NAME || TypedArrayBuffer::for_name(name).is_some() {
+            return ser::Serializer::serialize_newtype_struct(self.serializer, name, value);
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result {
+        let serializer = self.serializer;
+        let repr = serializer.enum_representation;
+        if let EnumRepresentation::Internal { tag } = repr {
+            // The payload is compared against the previous object itself, tag included.
+            let payload = DiffSerializer {
+                serializer,
+                previous: self.previous.clone(),
+                tag: Some((tag, variant)),
+            };
+            let value = ser::Serializer::serialize_newtype_struct(payload, variant, value)?;
+            let unchanged = Object::is(&value, &self.previous)
+                || (value.is_undefined() || value.is_null())
+                    && plain_object(&self.previous)
+                        .is_some_and(|prev| has_shape(prev, 0, Some((tag, variant))));
+            if unchanged {
+                return Ok(self.previous);
+            }
+            return serializer.merge_tagged(tag, variant, value);
+        }
+        let previous = self.previous_payload(variant);
+        let payload = DiffSerializer::new(serializer, previous.clone().unwrap_or_default());
+        let value = ser::Serializer::serialize_newtype_struct(payload, variant, value)
+            .map_err(|err| payload_error(repr, variant, err))?;
+        match previous {
+            Some(previous) if Object::is(&value, &previous) => Ok(self.previous),
+            _ => VariantSerializer::new(variant, serializer, value).end(Ok),
+        }
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(DiffArray::new(self.serializer, self.previous))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        if let EnumRepresentation::Internal { .. } = self.serializer.enum_representation {
+            return Err(internal_tuple_variant_error(variant));
+        }
+        let payload = self.previous_payload(variant);
+        Ok(DiffVariant {
+            variant,
+            serializer: self.serializer,
+            inner: DiffArray::new(self.serializer, payload.clone().unwrap_or_default()),
+            previous: payload.map(|payload| (self.previous, payload)),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(DiffMap::new(self.serializer, self.previous, self.tag))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(DiffObject::new(
+            self.serializer,
+            self.previous,
+            self.tag,
+            false,
+        ))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        let serializer = self.serializer;
+        let (inner, previous) = match serializer.enum_representation {
+            EnumRepresentation::Internal { tag } => {
+                let tag = Some((tag, variant));
+                (DiffObject::new(serializer, self.previous, tag, true), None)
+            }
+            _ => {
+                let payload = self.previous_payload(variant);
+                let inner =
+                    DiffObject::new(serializer, payload.clone().unwrap_or_default(), None, false);
+                (inner, payload.map(|payload| (self.previous, payload)))
+            }
+        };
+        Ok(DiffVariant {
+            variant,
+            serializer,
+            previous,
+            inner,
+        })
+    }
+}
+
+/// Like [`VariantSerializer`], but returns the previous value if the payload didn't change.
+struct DiffVariant<'s, S> {
+    variant: &'static str,
+    serializer: &'s Serializer,
+    /// The previous value and its payload, if it's the same variant.
+    previous: Option<(JsValue, JsValue)>,
+    inner: S,
+}
+
+impl<S> DiffVariant<'_, S> {
+    fn payload_error(&self, err: Error) -> Error {
+        payload_error(self.serializer.enum_representation, self.variant, err)
+    }
+
+    fn end(self, inner: impl FnOnce(S) -> Result) -> Result {
+        let value = inner(self.inner)?;
+        match self.previous {
+            Some((previous, payload)) if Object::is(&value, &payload) => Ok(previous),
+            _ => VariantSerializer::new(self.variant, self.serializer, value).end(Ok),
+        }
+    }
+}
+
+impl ser::SerializeTupleVariant for DiffVariant<'_, DiffArray<'_>> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+            .map_err(|err| self.payload_error(err))
+    }
+
+    fn end(self) -> Result {
+        self.end(ser::SerializeSeq::end)
+    }
+}
+
+impl ser::SerializeStructVariant for DiffVariant<'_, DiffObject<'_>> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+            .map_err(|err| self.payload_error(err))
+    }
+
+    fn end(self) -> Result {
+        self.end(ser::SerializeStruct::end)
+    }
+}
+
+struct DiffArray<'s> {
+    serializer: &'s Serializer,
+    previous: Option<Array>,
+    values: Vec<JsValue>,
+    changed: bool,
+}
+
+impl<'s> DiffArray<'s> {
+    fn new(serializer: &'s Serializer, previous: JsValue) -> Self {
+        let previous = Array::is_array(&previous).then(|| previous.unchecked_into::<Array>());
+        Self {
+            serializer,
+            changed: previous.is_none(),
+            previous,
+            values: Vec::new(),
+        }
+    }
+}
+
+impl ser::SerializeSeq for DiffArray<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let idx = self.values.len() as u32;
+        let previous = self
+            .previous
+            .as_ref()
+            .filter(|previous| idx < previous.length())
+            .map(|previous| previous.get(idx));
+        let value = diff_child(self.serializer, previous, value, &mut self.changed)
+            .map_err(|err| err.at(PathSegment::Index(idx)))?;
+        self.values.push(value);
+        Ok(())
+    }
+
+    fn end(self) -> Result {
+        match self.previous {
+            Some(previous) if !self.changed && previous.length() as usize == self.values.len() => {
+                Ok(previous.into())
+            }
+            _ => ser::SerializeSeq::end(ArraySerializer {
+                serializer: self.serializer,
+                idx: self.values.len() as u32,
+                target: self.values.into_iter().collect(),
+            }),
+        }
+    }
+}
+
+impl ser::SerializeTuple for DiffArray<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for DiffArray<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct DiffObject<'s> {
+    serializer: &'s Serializer,
+    previous: Option<ObjectExt>,
+    tag: Tag,
+    /// Whether a new object gets the tag as well, rather than having it merged in later.
+    write_tag: bool,
+    fields: Vec<(&'static str, JsValue)>,
+    changed: bool,
+}
+
+impl<'s> DiffObject<'s> {
+    fn new(serializer: &'s Serializer, previous: JsValue, tag: Tag, write_tag: bool) -> Self {
+        let previous = plain_object(&previous)
+            .is_some()
+            .then(|| previous.unchecked_into::<ObjectExt>());
+        Self {
+            serializer,
+            changed: previous.is_none(),
+            previous,
+            tag,
+            write_tag,
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl ser::SerializeStruct for DiffObject<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let previous = self
+            .previous
+            .as_ref()
+            .and_then(|previous| previous.get_own(&static_str_to_js(key).into(), key == PROTO));
+        let value = diff_child(self.serializer, previous, value, &mut self.changed)
+            .map_err(|err| err.at(PathSegment::Field(key.to_owned())))?;
+        self.fields.push((key, value));
+        Ok(())
+    }
+
+    fn end(self) -> Result {
+        if let Some(previous) = self.previous {
+            if !self.changed && has_shape(&previous, self.fields.len(), self.tag) {
+                return Ok(previous.into());
+            }
+        }
+        let target = match self.tag {
+            Some((tag, variant)) if self.write_tag => self.serializer.tagged_object(tag, variant),
+            _ => self.serializer.new_object(),
+        };
+        for (key, value) in self.fields {
+            target.set_static(key, value);
+        }
+        ser::SerializeStruct::end(ObjectSerializer {
+            serializer: self.serializer,
+            target,
+        })
+    }
+}
+
+struct DiffMap<'s> {
+    serializer: &'s Serializer,
+    /// The previous `Map`, or the previous object if maps are serialized as objects.
+    previous: Option<JsValue>,
+    tag: Tag,
+    /// Serialized keys and values, with whether each key is `__proto__`.
+    entries: Vec<(JsValue, bool, JsValue)>,
+    next_key: Option<(JsValue, bool)>,
+    changed: bool,
+}
+
+impl<'s> DiffMap<'s> {
+    fn new(serializer: &'s Serializer, previous: JsValue, tag: Tag) -> Self {
+        let reusable = if serializer.serialize_maps_as_objects {
+            plain_object(&previous).is_some()
+        } else {
+            previous.is_instance_of::<Map>()
+        };
+        Self {
+            serializer,
+            previous: reusable.then_some(previous),
+            tag,
+            entries: Vec::new(),
+            next_key: None,
+            changed: !reusable,
+        }
+    }
+
+    fn previous_value(&self, key: &JsValue, is_proto: bool) -> Option<JsValue> {
+        let previous = self.previous.as_ref()?;
+        if self.serializer.serialize_maps_as_objects {
+            previous.unchecked_ref::<ObjectExt>().get_own(key, is_proto)
+        } else {
+            let map = previous.unchecked_ref::<Map>();
+            map.has(key).then(|| map.get(key))
+        }
+    }
+}
+
+impl ser::SerializeMap for DiffMap<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        debug_assert!(self.next_key.is_none());
+        let as_object = self.serializer.serialize_maps_as_objects;
+        let key = key
+            .serialize(self.serializer)
+            .and_then(|key| match as_object {
+                true if !key.is_string() => Err(non_string_key_error()),
+                _ => Ok(key),
+            })
+            .map_err(|err| err.at(PathSegment::MapKey(self.entries.len() as u32)))?;
+        let is_proto = as_object && is_proto_key(&key);
+        self.next_key = Some((key, is_proto));
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let (key, is_proto) = self.next_key.take().unwrap_throw();
+        let previous = self.previous_value(&key, is_proto);
+        let value =
+            diff_child(self.serializer, previous, value, &mut self.changed).map_err(|err| {
+                err.at(match key.as_string() {
+                    Some(key) => PathSegment::Field(key),
+                    None => PathSegment::MapValue(self.entries.len() as u32),
+                })
+            })?;
+        self.entries.push((key, is_proto, value));
+        Ok(())
+    }
+
+    fn end(self) -> Result {
+        debug_assert!(self.next_key.is_none());
+        let as_object = self.serializer.serialize_maps_as_objects;
+        if let Some(previous) = self.previous {
+            let same_shape = if as_object {
+                has_shape(previous.unchecked_ref(), self.entries.len(), self.tag)
+            } else {
+                previous.unchecked_ref::<Map>().size() as usize == self.entries.len()
+            };
+            if !self.changed && same_shape {
+                return Ok(previous);
+            }
+        }
+        let map = MapSerializer::new(self.serializer, as_object);
+        for (key, is_proto, value) in self.entries {
+            map.insert(key, is_proto, value);
+        }
+        ser::SerializeMap::end(map)
+    }
+}
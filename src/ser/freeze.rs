@@ -0,0 +1,100 @@
+//! Protection of serialized values against mutation, see [`FreezeMode`].
+
+use super::*;
+use js_sys::{Function, Proxy, Reflect, TypeError};
+
+/// How the [`Serializer`] protects the objects, arrays and `Map`s it creates against mutation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FreezeMode {
+    /// Values are left mutable. This is the default.
+    Mutable,
+    /// Every object, array and `Map` is frozen with `Object.freeze`.
+    ///
+    /// As nested values are frozen too, the whole result is immutable, except for the
+    /// entries of `Map`s, which `Object.freeze` doesn't cover, `Uint8Array`s, which can't
+    /// be frozen, and values passed through as is with [`PreserveJsValue`](crate::PreserveJsValue).
+    Freeze,
+    /// Same as [`FreezeMode::Freeze`], but in debug builds every object, array and `Map`
+    /// is also wrapped in a `Proxy` that throws a `TypeError` on any attempt to modify it,
+    /// including calls to `Map.prototype.set`, instead of silently ignoring it.
+    ///
+    /// Proxies make every property access slower, so release builds only freeze values.
+    Throw,
+}
+
+impl FreezeMode {
+    /// Returns whether values are wrapped in proxies, which is only done in debug builds.
+    pub(crate) const fn uses_proxies(self) -> bool {
+        matches!(self, FreezeMode::Throw) && cfg!(debug_assertions)
+    }
+
+    /// Protects a newly created object, array or `Map` according to the mode.
+    pub(crate) fn apply(self, value: JsValue) -> JsValue {
+        if self == FreezeMode::Mutable {
+            return value;
+        }
+        Object::freeze(value.unchecked_ref::<Object>());
+        if self.uses_proxies() {
+            HANDLERS.with(|handlers| {
+                let handler = if value.is_instance_of::<Map>() {
+                    &handlers.map
+                } else {
+                    &handlers.object
+                };
+                Proxy::new(&value, handler).into()
+            })
+        } else {
+            value
+        }
+    }
+}
+
+/// Proxy handlers for [`FreezeMode::Throw`], created once per thread.
+struct Handlers {
+    object: Object,
+    map: Object,
+}
+
+thread_local! {
+    static HANDLERS: Handlers = Handlers::new();
+}
+
+fn modification_error() -> JsValue {
+    TypeError::new("Cannot modify a value serialized with FreezeMode::Throw").into()
+}
+
+impl Handlers {
+    fn new() -> Self {
+        let reject = Closure::<dyn Fn() -> std::result::Result<(), JsValue>>::new(|| {
+            Err(modification_error())
+        })
+        .into_js_value();
+
+        let object = Object::new().unchecked_into::<ObjectExt>();
+        for trap in ["set", "deleteProperty", "defineProperty", "setPrototypeOf"].iter() {
+            object.set_static(trap, reject.clone());
+        }
+        let map = Object::assign(&Object::new(), object.unchecked_ref::<Object>());
+        let map = map.unchecked_into::<ObjectExt>();
+
+        // `Map` methods only work with the `Map` itself as `this`, not with its proxy.
+        let get = Closure::<dyn Fn(JsValue, JsValue) -> JsValue>::new(
+            move |target: JsValue, key: JsValue| match key.as_string().as_deref() {
+                Some("set") | Some("delete") | Some("clear") => reject.clone(),
+                _ => {
+                    let value = Reflect::get(&target, &key).unwrap_or(JsValue::UNDEFINED);
+                    match value.dyn_ref::<Function>() {
+                        Some(method) => method.bind0(&target).into(),
+                        None => value,
+                    }
+                }
+            },
+        );
+        map.set_static("get", get.into_js_value());
+
+        Self {
+            object: object.unchecked_into(),
+            map: map.unchecked_into(),
+        }
+    }
+}
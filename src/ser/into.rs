@@ -0,0 +1,499 @@
+//! Serialization into existing JS values, see [`Serializer::serialize_into`].
+
+use super::*;
+use js_sys::{Reflect, Set};
+
+impl Serializer {
+    /// Serializes a value onto an existing JS object, array or `Map` instead of creating a new one.
+    ///
+    /// Structs, struct variants and maps serialized as objects write their fields onto a plain
+    /// `target` object, maps write their entries into a `Map` or onto a plain object, and
+    /// sequences overwrite the elements of an array from the start, growing it as needed.
+    /// Only the top-level container is reused, nested values are serialized as usual.
+    ///
+    /// Existing keys and elements which are not written stay in place unless
+    /// [`remove_stale_keys`](Self::remove_stale_keys) is set.
+    pub fn serialize_into<T: ?Sized + Serialize>(&self, value: &T, target: &JsValue) -> Result<()> {
+        value.serialize(IntoSerializer {
+            serializer: self,
+            target,
+            tag: None,
+        })
+    }
+}
+
+/// Describes the kind of a target for error messages.
+fn target_kind(target: &JsValue) -> &'static str {
+    if Array::is_array(target) {
+        "an array"
+    } else if target.is_instance_of::<Map>() {
+        "a Map"
+    } else if target.is_object() {
+        "an object"
+    } else {
+        "a primitive value"
+    }
+}
+
+fn target_error(what: &str, expected: &str, target: &JsValue) -> Error {
+    Error::with_kind(
+        ErrorKind::InvalidType,
+        format_args!("cannot serialize {} into {}", what, target_kind(target)),
+    )
+    .with_expected(expected)
+    .with_received(target_kind(target))
+}
+
+/// Keys of the target which have not been written yet, if they should be removed.
+struct Stale(Option<Set>);
+
+impl Stale {
+    fn new(serializer: &Serializer, keys: impl FnOnce() -> JsValue) -> Self {
+        Self(serializer.remove_stale_keys.then(|| Set::new(&keys())))
+    }
+
+    fn keep(&self, key: &JsValue) {
+        if let Some(stale) = &self.0 {
+            stale.delete(key);
+        }
+    }
+
+    fn remove(self, mut remove: impl FnMut(&JsValue)) {
+        if let Some(stale) = self.0 {
+            stale.for_each(&mut |key, _, _| remove(&key));
+        }
+    }
+}
+
+fn remove_property(target: &ObjectExt) -> impl FnMut(&JsValue) + '_ {
+    move |key| {
+        // Only fails for non-configurable properties, which are left in place.
+        let _ = Reflect::delete_property(target.unchecked_ref::<Object>(), key);
+    }
+}
+
+struct IntoSerializer<'s> {
+    serializer: &'s Serializer,
+    target: &'s JsValue,
+    /// Tag and variant name if the value is the payload of an internally tagged newtype variant.
+    tag: Option<(&'static str, &'static str)>,
+}
+
+impl<'s> IntoSerializer<'s> {
+    fn object(self, what: &str) -> Result<IntoObject<'s>> {
+        let target = plain_object(self.target)
+            .ok_or_else(|| target_error(what, "an object", self.target))?;
+        let object = IntoObject::new(self.serializer, target);
+        if let Some((tag, variant)) = self.tag {
+            object.write(tag, static_str_to_js(variant).into());
+        }
+        Ok(object)
+    }
+
+    fn unsupported(&self, what: &str) -> Error {
+        if let Some((_, variant)) = self.tag {
+            return non_object_internal_payload_error(variant);
+        }
+        target_error(what, "a struct, map, sequence or enum variant", self.target)
+    }
+}
+
+macro_rules! unsupported {
+    ($($name:ident($ty:ty) => $what:literal;)*) => {
+        $(fn $name(self, _v: $ty) -> Result<()> {
+            Err(self.unsupported($what))
+        })*
+    };
+}
+
+impl<'s> ser::Serializer for IntoSerializer<'s> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = IntoArray<'s>;
+    type SerializeTuple = IntoArray<'s>;
+    type SerializeTupleStruct = IntoArray<'s>;
+    type SerializeTupleVariant = IntoVariant<'s, ArraySerializer<'s>>;
+    type SerializeMap = IntoMap<'s>;
+    type SerializeStruct = IntoObject<'s>;
+    type SerializeStructVariant = IntoVariant<'s, ObjectSerializer<'s>>;
+
+    unsupported! {
+        serialize_bool(bool) => "a boolean";
+
+        serialize_i8(i8) => "a number";
+        serialize_i16(i16) => "a number";
+        serialize_i32(i32) => "a number";
+        serialize_i64(i64) => "a number";
+        serialize_i128(i128) => "a number";
+
+        serialize_u8(u8) => "a number";
+        serialize_u16(u16) => "a number";
+        serialize_u32(u32) => "a number";
+        serialize_u64(u64) => "a number";
+        serialize_u128(u128) => "a number";
+
+        serialize_f32(f32) => "a number";
+        serialize_f64(f64) => "a number";
+
+        serialize_char(char) => "a char";
+        serialize_str(&str) => "a string";
+        serialize_bytes(&[u8]) => "bytes";
+        serialize_unit_struct(&'static str) => "a unit struct";
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        // An internally tagged newtype variant without a payload only consists of the tag.
+        if self.tag.is_some() {
+            return ser::SerializeStruct::end(self.object("a unit")?);
+        }
+        Err(self.unsupported("a unit"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        match self.serializer.enum_representation {
+            EnumRepresentation::External => Err(self.unsupported("a unit variant")),
+            EnumRepresentation::Internal { tag } | EnumRepresentation::Adjacent { tag, .. } => {
+                let tag = Some((tag, variant));
+                ser::SerializeStruct::end(IntoSerializer { tag, ..self }.object("a unit variant")?)
+            }
+        }
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let repr = self.serializer.enum_representation;
+        if let EnumRepresentation::Internal { tag } = repr {
+            return value.serialize(IntoSerializer {
+                tag: Some((tag, variant)),
+                ..self
+            });
+        }
+        let object = self.object("an enum variant")?;
+        let value = ser::Serializer::serialize_newtype_struct(object.serializer, variant, value)
+            .map_err(|err| payload_error(repr, variant, err))?;
+        write_variant(&object, variant, value);
+        ser::SerializeStruct::end(object)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        if self.tag.is_some() {
+            return Err(self.unsupported("a sequence"));
+        }
+        if !Array::is_array(self.target) {
+            return Err(target_error("a sequence", "an array", self.target));
+        }
+        Ok(IntoArray {
+            inner: ArraySerializer {
+                serializer: self.serializer,
+                target: self.target.clone().unchecked_into(),
+                idx: 0,
+            },
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        if let EnumRepresentation::Internal { .. } = self.serializer.enum_representation {
+            return Err(internal_tuple_variant_error(variant));
+        }
+        let serializer = self.serializer;
+        Ok(IntoVariant {
+            variant,
+            object: self.object("an enum variant")?,
+            payload: Some(ArraySerializer::new(serializer)),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        let serializer = self.serializer;
+        let (target, stale) = if let Some(map) = self.target.dyn_ref::<Map>() {
+            if self.tag.is_some() {
+                return Err(self.unsupported("a map"));
+            }
+            let stale = Stale::new(serializer, || map.keys().into());
+            (MapResult::Map(map.clone()), stale)
+        } else {
+            let object = self.object("a map")?;
+            let target = object.target.unchecked_ref::<Object>().clone();
+            (MapResult::Object(target), object.stale)
+        };
+        Ok(IntoMap {
+            inner: MapSerializer {
+                serializer,
+                target,
+                next_key: None,
+                idx: 0,
+                null_prototype: false,
+            },
+            stale,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.object("a struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        let serializer = self.serializer;
+        let (object, payload) = match serializer.enum_representation {
+            EnumRepresentation::Internal { tag } => {
+                let tag = Some((tag, variant));
+                (
+                    IntoSerializer { tag, ..self }.object("an enum variant")?,
+                    None,
+                )
+            }
+            _ => (
+                self.object("an enum variant")?,
+                Some(ObjectSerializer::new(serializer)),
+            ),
+        };
+        Ok(IntoVariant {
+            variant,
+            object,
+            payload,
+        })
+    }
+}
+
+struct IntoArray<'s> {
+    inner: ArraySerializer<'s>,
+}
+
+impl ser::SerializeSeq for IntoArray<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<()> {
+        if self.inner.serializer.remove_stale_keys {
+            self.inner.target.set_length(self.inner.idx);
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for IntoArray<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for IntoArray<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct IntoObject<'s> {
+    serializer: &'s Serializer,
+    target: &'s ObjectExt,
+    stale: Stale,
+}
+
+impl<'s> IntoObject<'s> {
+    fn new(serializer: &'s Serializer, target: &'s ObjectExt) -> Self {
+        Self {
+            serializer,
+            target,
+            stale: Stale::new(serializer, || {
+                Object::keys(target.unchecked_ref::<Object>()).into()
+            }),
+        }
+    }
+
+    fn write(&self, key: &'static str, value: JsValue) {
+        self.target.set_static(key, value);
+        self.stale.keep(&static_str_to_js(key));
+    }
+}
+
+impl ser::SerializeStruct for IntoObject<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let value = value
+            .serialize(self.serializer)
+            .map_err(|err| err.at(PathSegment::Field(key.to_owned())))?;
+        self.write(key, value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        self.stale.remove(remove_property(self.target));
+        Ok(())
+    }
+}
+
+struct IntoMap<'s> {
+    inner: MapSerializer<'s>,
+    stale: Stale,
+}
+
+impl ser::SerializeMap for IntoMap<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        ser::SerializeMap::serialize_key(&mut self.inner, key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self.inner.next_key.clone().map(|(key, _)| key);
+        ser::SerializeMap::serialize_value(&mut self.inner, value)?;
+        if let Some(key) = key {
+            self.stale.keep(&key);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        match &self.inner.target {
+            MapResult::Map(map) => self.stale.remove(|key| {
+                map.delete(key);
+            }),
+            MapResult::Object(object) => self.stale.remove(remove_property(object.unchecked_ref())),
+        }
+        Ok(())
+    }
+}
+
+/// Writes an externally or adjacently tagged variant onto the target object, or the fields
+/// of an internally tagged one, which share the object with the tag.
+struct IntoVariant<'s, S> {
+    variant: &'static str,
+    object: IntoObject<'s>,
+    /// Serializer for the payload, unless it's written directly onto the target.
+    payload: Option<S>,
+}
+
+/// Writes the properties of an externally or adjacently tagged variant onto the target.
+fn write_variant(object: &IntoObject, variant: &'static str, value: JsValue) {
+    match object.serializer.enum_representation {
+        EnumRepresentation::External => object.write(variant, value),
+        EnumRepresentation::Adjacent { tag, content } => {
+            object.write(tag, static_str_to_js(variant).into());
+            object.write(content, value);
+        }
+        EnumRepresentation::Internal { .. } => unreachable!(),
+    }
+}
+
+impl<S> IntoVariant<'_, S> {
+    fn end(self, end: impl FnOnce(S) -> Result) -> Result<()> {
+        if let Some(payload) = self.payload {
+            write_variant(&self.object, self.variant, end(payload)?);
+        }
+        ser::SerializeStruct::end(self.object)
+    }
+}
+
+impl ser::SerializeTupleVariant for IntoVariant<'_, ArraySerializer<'_>> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let repr = self.object.serializer.enum_representation;
+        let payload = self.payload.as_mut().unwrap_throw();
+        ser::SerializeSeq::serialize_element(payload, value)
+            .map_err(|err| payload_error(repr, self.variant, err))
+    }
+
+    fn end(self) -> Result<()> {
+        self.end(ser::SerializeSeq::end)
+    }
+}
+
+impl ser::SerializeStructVariant for IntoVariant<'_, ObjectSerializer<'_>> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let repr = self.object.serializer.enum_representation;
+        match &mut self.payload {
+            Some(payload) => ser::SerializeStruct::serialize_field(payload, key, value)
+                .map_err(|err| payload_error(repr, self.variant, err)),
+            None => ser::SerializeStruct::serialize_field(&mut self.object, key, value),
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        self.end(ser::SerializeStruct::end)
+    }
+}
@@ -0,0 +1,530 @@
+//! Adapters for converting numeric sequences to and from JavaScript typed arrays.
+//!
+//! By default, sequences are converted into plain JavaScript arrays one element at a time.
+//! For large numeric sequences it's much faster to use these adapters, which copy all the
+//! elements into the matching typed array at once:
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Geometry {
+//!     // Converted to and from a `Float64Array`.
+//!     #[serde(with = "serde_wasm_bindgen::typed_array")]
+//!     coordinates: Vec<f64>,
+//!     // Converted to and from an `Int32Array`.
+//!     #[serde(with = "serde_wasm_bindgen::typed_array")]
+//!     indices: [i32; 3],
+//! }
+//! ```
+//!
+//! `i64` and `u64` sequences are converted into `BigInt64Array` and `BigUint64Array`
+//! respectively.
+//!
+//! When deserializing, typed arrays of a different element type are rejected, while plain
+//! JavaScript arrays and other iterables are still accepted and converted element by element.
+//!
+//! Serializers and deserializers other than the ones from this crate see a regular sequence,
+//! so the same types can still be used with other formats.
+
+use js_sys::{
+    BigInt64Array, BigUint64Array, Float32Array, Float64Array, Int16Array, Int32Array, Int8Array,
+    Uint16Array, Uint32Array, Uint8Array,
+};
+use serde::de::{self, DeserializeOwned, IntoDeserializer};
+use serde::ser::{self, Error as _, Impossible, Serialize};
+use serde::Deserialize;
+use std::any::Any;
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{Error, Result};
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Numeric types that have a corresponding JavaScript typed array.
+///
+/// This trait is sealed and can't be implemented outside of this crate.
+pub trait TypedArrayElement:
+    Copy + Serialize + DeserializeOwned + private::Sealed + 'static
+{
+    #[doc(hidden)]
+    const NAME: &'static str;
+
+    #[doc(hidden)]
+    fn slice_to_js(elements: &[Self]) -> JsValue;
+}
+
+/// A slice being serialized by [`serialize`], handed over to the serializers of this crate.
+#[derive(Clone, Copy)]
+struct RawElements {
+    name: &'static str,
+    ptr: *const (),
+    len: usize,
+    to_js: unsafe fn(*const (), usize) -> JsValue,
+}
+
+thread_local! {
+    /// Hands the elements of a slice over to [`crate::Serializer`], so that they can be copied
+    /// into a typed array straight from the slice.
+    static SERIALIZED: Cell<Option<RawElements>> = const { Cell::new(None) };
+
+    /// Hands the elements copied from a typed array over to the visitor of [`deserialize`].
+    static DESERIALIZED: Cell<Option<Box<dyn Any>>> = const { Cell::new(None) };
+}
+
+unsafe fn raw_elements_to_js<E: TypedArrayElement>(ptr: *const (), len: usize) -> JsValue {
+    E::slice_to_js(std::slice::from_raw_parts(ptr.cast::<E>(), len))
+}
+
+/// Clears the slice handed over by [`serialize`] once the borrow ends, even on panic.
+struct HandOverGuard;
+
+impl Drop for HandOverGuard {
+    fn drop(&mut self) {
+        SERIALIZED.with(Cell::take);
+    }
+}
+
+/// Copies the slice handed over by [`serialize`] into a new typed array if it belongs to
+/// the adapter with the given name.
+pub(crate) fn take_serialized(name: &str) -> Option<JsValue> {
+    let elements = SERIALIZED
+        .with(Cell::take)
+        .filter(|elements| elements.name == name)?;
+    // SAFETY: the slice is only handed over for the duration of the `serialize` call that
+    // borrows it, and its name identifies the element type.
+    Some(unsafe { (elements.to_js)(elements.ptr, elements.len) })
+}
+
+/// Serializes a numeric sequence into a JavaScript typed array.
+///
+/// Works with anything that can be borrowed as a slice, like `Vec<T>`, `Box<[T]>` and `[T; N]`.
+pub fn serialize<S, T, E>(value: &T, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: ser::Serializer,
+    T: AsRef<[E]> + ?Sized,
+    E: TypedArrayElement,
+{
+    let elements = value.as_ref();
+    SERIALIZED.with(|slot| {
+        slot.set(Some(RawElements {
+            name: E::NAME,
+            ptr: elements.as_ptr().cast(),
+            len: elements.len(),
+            to_js: raw_elements_to_js::<E>,
+        }))
+    });
+    let _guard = HandOverGuard;
+    // Our serializer copies the slice at once, while other serializers,
+    // e.g. `serde_json`, see a regular sequence.
+    serializer.serialize_newtype_struct(E::NAME, elements)
+}
+
+/// Takes the elements handed over by [`TypedArrayBuffer::visit_seq`] if they have the given type.
+fn take_deserialized<E: TypedArrayElement>() -> Option<Vec<E>> {
+    let elements = DESERIALIZED.with(Cell::take)?;
+    match elements.downcast::<Vec<E>>() {
+        Ok(elements) => Some(*elements),
+        Err(elements) => {
+            DESERIALIZED.with(|slot| slot.set(Some(elements)));
+            None
+        }
+    }
+}
+
+/// A sequence of elements copied from a typed array, which are only taken out of
+/// [`DESERIALIZED`] if the visitor actually iterates over them.
+struct HandedOverSeq<E> {
+    len: usize,
+    elements: Option<std::vec::IntoIter<E>>,
+}
+
+impl<'de, E> de::SeqAccess<'de> for HandedOverSeq<E>
+where
+    E: TypedArrayElement + IntoDeserializer<'de, Error>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        let elements = self
+            .elements
+            .get_or_insert_with(|| take_deserialized::<E>().unwrap_or_default().into_iter());
+        match elements.next() {
+            Some(element) => seed.deserialize(element.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(
+            self.elements
+                .as_ref()
+                .map_or(self.len, ExactSizeIterator::len),
+        )
+    }
+}
+
+/// Containers that can be filled from a JavaScript typed array by [`deserialize`].
+pub trait TypedArrayTarget: Sized {
+    /// Element type of the corresponding typed array.
+    type Element: TypedArrayElement;
+
+    /// Creates the container from the copied elements, failing if their number doesn't fit.
+    fn from_elements<Err: de::Error>(
+        elements: Vec<Self::Element>,
+    ) -> std::result::Result<Self, Err>;
+}
+
+impl<E: TypedArrayElement> TypedArrayTarget for Vec<E> {
+    type Element = E;
+
+    fn from_elements<Err: de::Error>(elements: Vec<E>) -> std::result::Result<Self, Err> {
+        Ok(elements)
+    }
+}
+
+impl<E: TypedArrayElement> TypedArrayTarget for Box<[E]> {
+    type Element = E;
+
+    fn from_elements<Err: de::Error>(elements: Vec<E>) -> std::result::Result<Self, Err> {
+        Ok(elements.into_boxed_slice())
+    }
+}
+
+impl<E: TypedArrayElement, const N: usize> TypedArrayTarget for [E; N] {
+    type Element = E;
+
+    fn from_elements<Err: de::Error>(elements: Vec<E>) -> std::result::Result<Self, Err> {
+        Self::try_from(elements).map_err(|elements| {
+            Err::invalid_length(
+                elements.len(),
+                &format!("an array of length {}", N).as_str(),
+            )
+        })
+    }
+}
+
+/// Deserializes a numeric container from a JavaScript typed array of the matching type.
+///
+/// Works with `Vec<T>`, `Box<[T]>` and `[T; N]`.
+pub fn deserialize<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: TypedArrayTarget,
+{
+    struct ElementsVisitor<E>(PhantomData<E>);
+
+    impl<'de, E: TypedArrayElement> de::Visitor<'de> for ElementsVisitor<E> {
+        type Value = Vec<E>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence of numbers")
+        }
+
+        fn visit_newtype_struct<D: de::Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> std::result::Result<Self::Value, D::Error> {
+            Vec::deserialize(deserializer)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> std::result::Result<Self::Value, A::Error> {
+            // Elements copied from a typed array are taken over as a whole.
+            if let Some(elements) = take_deserialized() {
+                return Ok(elements);
+            }
+            let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(element) = seq.next_element()? {
+                elements.push(element);
+            }
+            Ok(elements)
+        }
+    }
+
+    let elements = deserializer.deserialize_newtype_struct(
+        <T::Element as TypedArrayElement>::NAME,
+        ElementsVisitor(PhantomData),
+    )?;
+    T::from_elements(elements)
+}
+
+macro_rules! typed_arrays {
+    ($($variant:ident($ty:ident, $array:ident, $serialize:ident);)*) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl TypedArrayElement for $ty {
+                const NAME: &'static str = concat!("$serde_wasm_bindgen::", stringify!($array));
+
+                fn slice_to_js(elements: &[Self]) -> JsValue {
+                    $array::from(elements).into()
+                }
+            }
+        )*
+
+        /// Elements of a typed array collected on the Rust side before the bulk copy.
+        pub(crate) enum TypedArrayBuffer {
+            $($variant(Vec<$ty>),)*
+        }
+
+        impl TypedArrayBuffer {
+            /// Returns an empty buffer if the name belongs to one of the typed array adapters.
+            pub(crate) fn for_name(name: &'static str) -> Option<Self> {
+                $(if name == <$ty as TypedArrayElement>::NAME {
+                    return Some(Self::$variant(Vec::new()));
+                })*
+                None
+            }
+
+            pub(crate) const fn array_name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant(_) => stringify!($array),)*
+                }
+            }
+
+            fn reserve(&mut self, additional: usize) {
+                match self {
+                    $(Self::$variant(buf) => buf.reserve(additional),)*
+                }
+            }
+
+            /// Copies all the elements of a JS typed array of the matching type into the buffer.
+            ///
+            /// Returns `false` if the value is not such a typed array.
+            pub(crate) fn fill_from(&mut self, value: &JsValue) -> bool {
+                match self {
+                    $(Self::$variant(buf) => match value.dyn_ref::<$array>() {
+                        Some(array) => {
+                            *buf = array.to_vec();
+                            true
+                        }
+                        None => false,
+                    },)*
+                }
+            }
+
+            /// Feeds the collected elements to the visitor as a sequence.
+            ///
+            /// The visitor of [`deserialize`] takes the whole buffer at once, while any other
+            /// visitor, e.g. the one buffering untagged enums, sees a regular sequence.
+            pub(crate) fn visit_seq<'de, V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                match self {
+                    $(Self::$variant(buf) => {
+                        let len = buf.len();
+                        DESERIALIZED.with(|slot| slot.set(Some(Box::new(buf))));
+                        let result = visitor.visit_seq(HandedOverSeq::<$ty> { len, elements: None });
+                        DESERIALIZED.with(Cell::take);
+                        result
+                    })*
+                }
+            }
+
+            /// Copies all the collected elements into a new JS typed array.
+            pub(crate) fn into_js(self) -> JsValue {
+                match self {
+                    $(Self::$variant(buf) => $array::from(buf.as_slice()).into(),)*
+                }
+            }
+        }
+
+        impl<'a> ser::Serializer for &'a mut TypedArrayBuffer {
+            type Ok = ();
+            type Error = Error;
+
+            type SerializeSeq = Self;
+            type SerializeTuple = Self;
+            type SerializeTupleStruct = Impossible<(), Error>;
+            type SerializeTupleVariant = Impossible<(), Error>;
+            type SerializeMap = Impossible<(), Error>;
+            type SerializeStruct = Impossible<(), Error>;
+            type SerializeStructVariant = Impossible<(), Error>;
+
+            $(fn $serialize(self, v: $ty) -> Result<()> {
+                match self {
+                    TypedArrayBuffer::$variant(buf) => {
+                        buf.push(v);
+                        Ok(())
+                    }
+                    _ => Err(self.unexpected(stringify!($ty))),
+                }
+            })*
+
+            fn serialize_bool(self, _v: bool) -> Result<()> {
+                Err(self.unexpected("bool"))
+            }
+
+            fn serialize_i128(self, _v: i128) -> Result<()> {
+                Err(self.unexpected("i128"))
+            }
+
+            fn serialize_u128(self, _v: u128) -> Result<()> {
+                Err(self.unexpected("u128"))
+            }
+
+            fn serialize_char(self, _v: char) -> Result<()> {
+                Err(self.unexpected("char"))
+            }
+
+            fn serialize_str(self, _v: &str) -> Result<()> {
+                Err(self.unexpected("string"))
+            }
+
+            fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+                Err(self.unexpected("bytes"))
+            }
+
+            fn serialize_none(self) -> Result<()> {
+                Err(self.unexpected("none"))
+            }
+
+            fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<()> {
+                Err(self.unexpected("some"))
+            }
+
+            fn serialize_unit(self) -> Result<()> {
+                Err(self.unexpected("unit"))
+            }
+
+            fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+                Err(self.unexpected("unit struct"))
+            }
+
+            fn serialize_unit_variant(
+                self,
+                _name: &'static str,
+                _variant_index: u32,
+                _variant: &'static str,
+            ) -> Result<()> {
+                Err(self.unexpected("unit variant"))
+            }
+
+            fn serialize_newtype_struct<T: ?Sized + Serialize>(
+                self,
+                _name: &'static str,
+                value: &T,
+            ) -> Result<()> {
+                value.serialize(self)
+            }
+
+            fn serialize_newtype_variant<T: ?Sized + Serialize>(
+                self,
+                _name: &'static str,
+                _variant_index: u32,
+                _variant: &'static str,
+                _value: &T,
+            ) -> Result<()> {
+                Err(self.unexpected("newtype variant"))
+            }
+
+            fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+                self.reserve(len.unwrap_or_default());
+                Ok(self)
+            }
+
+            fn serialize_tuple(self, len: usize) -> Result<Self> {
+                self.serialize_seq(Some(len))
+            }
+
+            fn serialize_tuple_struct(
+                self,
+                _name: &'static str,
+                _len: usize,
+            ) -> Result<Self::SerializeTupleStruct> {
+                Err(self.unexpected("tuple struct"))
+            }
+
+            fn serialize_tuple_variant(
+                self,
+                _name: &'static str,
+                _variant_index: u32,
+                _variant: &'static str,
+                _len: usize,
+            ) -> Result<Self::SerializeTupleVariant> {
+                Err(self.unexpected("tuple variant"))
+            }
+
+            fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+                Err(self.unexpected("map"))
+            }
+
+            fn serialize_struct(
+                self,
+                _name: &'static str,
+                _len: usize,
+            ) -> Result<Self::SerializeStruct> {
+                Err(self.unexpected("struct"))
+            }
+
+            fn serialize_struct_variant(
+                self,
+                _name: &'static str,
+                _variant_index: u32,
+                _variant: &'static str,
+                _len: usize,
+            ) -> Result<Self::SerializeStructVariant> {
+                Err(self.unexpected("struct variant"))
+            }
+        }
+    };
+}
+
+typed_arrays! {
+    I8(i8, Int8Array, serialize_i8);
+    U8(u8, Uint8Array, serialize_u8);
+    I16(i16, Int16Array, serialize_i16);
+    U16(u16, Uint16Array, serialize_u16);
+    I32(i32, Int32Array, serialize_i32);
+    U32(u32, Uint32Array, serialize_u32);
+    I64(i64, BigInt64Array, serialize_i64);
+    U64(u64, BigUint64Array, serialize_u64);
+    F32(f32, Float32Array, serialize_f32);
+    F64(f64, Float64Array, serialize_f64);
+}
+
+impl TypedArrayBuffer {
+    fn unexpected(&self, found: &str) -> Error {
+        Error::custom(format_args!(
+            "cannot serialize {} as an element of {}",
+            found,
+            self.array_name()
+        ))
+    }
+}
+
+impl ser::SerializeSeq for &mut TypedArrayBuffer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut TypedArrayBuffer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
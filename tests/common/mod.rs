@@ -1,10 +1,14 @@
-use js_sys::{BigInt, JsString, Number, Object};
+use js_sys::{Array, BigInt, JsString, Number, Object};
 use maplit::{btreemap, hashmap, hashset};
 use proptest::prelude::*;
 use serde::de::DeserializeOwned;
 use serde::ser::Error as SerError;
 use serde::{Deserialize, Serialize};
-use serde_wasm_bindgen::{from_value, to_value, Error, PreserveJsValue, Serializer};
+use serde_wasm_bindgen::{
+    from_value, from_value_with, to_value, DeserializerConfig, EnumRepresentation, Error,
+    ErrorKind, FreezeMode, Merge, NumberPolicy, Patch, PathSegment, PreserveJsValue, Serializer,
+    UnknownFields,
+};
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -455,6 +459,118 @@ mod compat {
     }
 }
 
+#[wasm_bindgen_test]
+fn number_policy() {
+    fn test_policy<T>(policy: NumberPolicy, value: T, expected: JsValue)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        let serialized = value
+            .serialize(&Serializer::new().number_policy(policy))
+            .unwrap();
+        assert_eq!(serialized, expected, "{:?} with {:?}", value, policy);
+        let config = DeserializerConfig::new().number_policy(policy);
+        assert_eq!(from_value_with::<T>(serialized, &config).unwrap(), value);
+    }
+
+    let policy = NumberPolicy::NumberOrBigInt;
+    test_policy(policy, 1_i64, JsValue::from(1));
+    test_policy(policy, i64::MIN, JsValue::from(i64::MIN));
+    test_policy(policy, 1_u128, JsValue::from(1));
+    test_policy(policy, u128::MAX, JsValue::from(u128::MAX));
+
+    let policy = NumberPolicy::BigInt;
+    test_policy(policy, 1_u64, JsValue::from(1_u64));
+    test_policy(policy, -1_i128, JsValue::from(-1_i128));
+
+    let policy = NumberPolicy::Number;
+    test_policy(policy, -1_i64, JsValue::from(-1));
+    test_policy(policy, 1_u128, JsValue::from(1));
+    u64::MAX
+        .serialize(&Serializer::new().number_policy(policy))
+        .unwrap_err();
+    i128::MIN
+        .serialize(&Serializer::new().number_policy(policy))
+        .unwrap_err();
+
+    let policy = NumberPolicy::String;
+    test_policy(policy, 1_u64, JsValue::from("1"));
+    test_policy(policy, i64::MIN, JsValue::from(i64::MIN.to_string()));
+    test_policy(policy, u128::MAX, JsValue::from(u128::MAX.to_string()));
+    let config = DeserializerConfig::new().number_policy(policy);
+    from_value_with::<u64>("-1".into(), &config).unwrap_err();
+    from_value_with::<i128>("1.5".into(), &config).unwrap_err();
+    // Decimal strings are only accepted with the string policy.
+    from_value::<u64>("1".into()).unwrap_err();
+}
+
+#[wasm_bindgen_test]
+fn coerce_primitives() {
+    let config = DeserializerConfig::new().coerce_primitives(true);
+
+    // Small BigInts for narrow integers, with range checks still applied.
+    assert_eq!(
+        from_value_with::<i8>(JsValue::from(-5_i64), &config).unwrap(),
+        -5
+    );
+    assert_eq!(
+        from_value_with::<u32>(JsValue::from(42_u64), &config).unwrap(),
+        42
+    );
+    from_value_with::<i8>(JsValue::from(300_i64), &config).unwrap_err();
+    from_value_with::<u16>(JsValue::from(-1_i64), &config).unwrap_err();
+    // BigInts beyond 64 bits are out of range just like slightly too large ones.
+    let huge = js_sys::BigInt::from(2).pow(&js_sys::BigInt::from(70));
+    let err = from_value_with::<u8>(huge.into(), &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    let err = from_value_with::<u8>(JsValue::from(300_i64), &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+
+    // Safe integer numbers for 128-bit integers.
+    assert_eq!(
+        from_value_with::<i128>(JsValue::from(-7), &config).unwrap(),
+        -7
+    );
+    assert_eq!(
+        from_value_with::<u128>(JsValue::from(7), &config).unwrap(),
+        7
+    );
+    from_value_with::<u128>(JsValue::from(1.5), &config).unwrap_err();
+
+    // Numeric strings.
+    assert_eq!(from_value_with::<u32>("42".into(), &config).unwrap(), 42);
+    assert_eq!(from_value_with::<i64>("-42".into(), &config).unwrap(), -42);
+    assert_eq!(from_value_with::<f64>("1.5".into(), &config).unwrap(), 1.5);
+    from_value_with::<u8>("256".into(), &config).unwrap_err();
+    from_value_with::<u32>("forty-two".into(), &config).unwrap_err();
+
+    // Boxed primitives.
+    let boxed = |value: JsValue| {
+        js_sys::Function::new_with_args("value", "return Object(value)")
+            .call1(&JsValue::UNDEFINED, &value)
+            .unwrap()
+    };
+    assert_eq!(from_value_with::<u8>(boxed(3.into()), &config).unwrap(), 3);
+    assert_eq!(
+        from_value_with::<f64>(boxed(3.into()), &config).unwrap(),
+        3.0
+    );
+    assert_eq!(
+        from_value_with::<String>(boxed("abc".into()), &config).unwrap(),
+        "abc"
+    );
+    assert_eq!(
+        from_value_with::<char>(boxed("a".into()), &config).unwrap(),
+        'a'
+    );
+    assert!(from_value_with::<bool>(boxed(true.into()), &config).unwrap());
+
+    // None of this is accepted by default.
+    from_value::<i8>(JsValue::from(1_i64)).unwrap_err();
+    from_value::<u32>("42".into()).unwrap_err();
+    from_value::<u8>(boxed(3.into())).unwrap_err();
+}
+
 #[wasm_bindgen_test]
 fn bytes() {
     // Create a backing storage.
@@ -505,6 +621,90 @@ fn bytes_from_mixed_array() {
     from_value::<serde_bytes::ByteBuf>(value).unwrap_err();
 }
 
+#[wasm_bindgen_test]
+fn typed_arrays() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Geometry {
+        #[serde(with = "serde_wasm_bindgen::typed_array")]
+        coordinates: Vec<f64>,
+        #[serde(with = "serde_wasm_bindgen::typed_array")]
+        indices: [i32; 3],
+        #[serde(with = "serde_wasm_bindgen::typed_array")]
+        ids: Box<[u64]>,
+        #[serde(with = "serde_wasm_bindgen::typed_array")]
+        empty: Vec<f32>,
+    }
+
+    let geometry = Geometry {
+        coordinates: vec![1.5, -2.0, 3.25],
+        indices: [0, -1, 2],
+        ids: vec![1, u64::MAX].into_boxed_slice(),
+        empty: vec![],
+    };
+    let value = to_value(&geometry).unwrap();
+    let get = |key: &str| js_sys::Reflect::get(&value, &key.into()).unwrap();
+    assert_eq!(
+        get("coordinates")
+            .dyn_into::<js_sys::Float64Array>()
+            .unwrap()
+            .to_vec(),
+        geometry.coordinates
+    );
+    assert_eq!(
+        get("indices")
+            .dyn_into::<js_sys::Int32Array>()
+            .unwrap()
+            .to_vec(),
+        geometry.indices
+    );
+    assert_eq!(
+        get("ids")
+            .dyn_into::<js_sys::BigUint64Array>()
+            .unwrap()
+            .to_vec(),
+        geometry.ids.as_ref()
+    );
+    assert_eq!(
+        get("empty")
+            .dyn_into::<js_sys::Float32Array>()
+            .unwrap()
+            .length(),
+        0
+    );
+
+    assert_eq!(from_value::<Geometry>(value).unwrap(), geometry);
+
+    // Other serializers see plain sequences.
+    let json = serde_json::to_string(&geometry).unwrap();
+    assert_eq!(
+        json,
+        r#"{"coordinates":[1.5,-2.0,3.25],"indices":[0,-1,2],"ids":[1,18446744073709551615],"empty":[]}"#
+    );
+    assert_eq!(serde_json::from_str::<Geometry>(&json).unwrap(), geometry);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Samples(#[serde(with = "serde_wasm_bindgen::typed_array")] [f32; 2]);
+
+    let samples = js_sys::Float32Array::from(&[0.5_f32, 1.0][..]);
+    assert_eq!(
+        from_value::<Samples>(samples.into()).unwrap(),
+        Samples([0.5, 1.0])
+    );
+    // Plain arrays are still accepted.
+    let samples = to_value(&[0.5_f32, 1.0]).unwrap();
+    assert_eq!(from_value::<Samples>(samples).unwrap(), Samples([0.5, 1.0]));
+    // Typed arrays of other element types are not.
+    let samples = js_sys::Float64Array::from(&[0.5, 1.0][..]);
+    let err = from_value::<Samples>(samples.into()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: Float64Array, expected Float32Array"
+    );
+    // Neither are typed arrays of the wrong length.
+    let samples = js_sys::Float32Array::from(&[0.5_f32][..]);
+    from_value::<Samples>(samples.into()).unwrap_err();
+}
+
 #[wasm_bindgen_test]
 fn options() {
     test_via_into(Some(0_u32), 0_u32);
@@ -646,6 +846,141 @@ fn enums() {
     }
 }
 
+#[wasm_bindgen_test]
+fn enum_representations() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Plain {
+        Unit,
+        Newtype(BTreeMap<String, i32>),
+        Struct { a: String, b: i32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "type")]
+    enum Internal {
+        Unit,
+        Newtype(BTreeMap<String, i32>),
+        Struct { a: String, b: i32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "t", content = "c")]
+    enum Adjacent {
+        Unit,
+        Newtype(BTreeMap<String, i32>),
+        Struct { a: String, b: i32 },
+    }
+
+    fn test_repr<T>(repr: EnumRepresentation, plain: Plain, tagged: T)
+    where
+        T: Serialize + Debug,
+    {
+        let serializer = Serializer::json_compatible().enum_representation(repr);
+        let value = plain.serialize(&serializer).unwrap();
+        assert_eq!(
+            js_sys::JSON::stringify(&value).unwrap(),
+            serde_json::to_string(&tagged).unwrap(),
+        );
+        let config = DeserializerConfig::strict().enum_representation(repr);
+        assert_eq!(from_value_with::<Plain>(value, &config).unwrap(), plain);
+    }
+
+    let internal = EnumRepresentation::Internal { tag: "type" };
+    test_repr(internal, Plain::Unit, Internal::Unit);
+    test_repr(
+        internal,
+        Plain::Newtype(btreemap! { "a".to_string() => 1 }),
+        Internal::Newtype(btreemap! { "a".to_string() => 1 }),
+    );
+    test_repr(
+        internal,
+        Plain::Struct {
+            a: "x".to_string(),
+            b: 42,
+        },
+        Internal::Struct {
+            a: "x".to_string(),
+            b: 42,
+        },
+    );
+
+    let adjacent = EnumRepresentation::Adjacent {
+        tag: "t",
+        content: "c",
+    };
+    test_repr(adjacent, Plain::Unit, Adjacent::Unit);
+    test_repr(
+        adjacent,
+        Plain::Newtype(btreemap! { "a".to_string() => 1 }),
+        Adjacent::Newtype(btreemap! { "a".to_string() => 1 }),
+    );
+    test_repr(
+        adjacent,
+        Plain::Struct {
+            a: "x".to_string(),
+            b: 42,
+        },
+        Adjacent::Struct {
+            a: "x".to_string(),
+            b: 42,
+        },
+    );
+
+    // Unit variants are still accepted as plain strings.
+    assert_eq!(
+        from_value_with::<Plain>(
+            "Unit".into(),
+            &DeserializerConfig::new().enum_representation(internal)
+        )
+        .unwrap(),
+        Plain::Unit
+    );
+
+    // The tag is hidden from newtype payloads without modifying the input, even if it's frozen.
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Point {
+        x: i32,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Point(Point),
+    }
+
+    let strict = DeserializerConfig::strict().enum_representation(internal);
+    let input = js_sys::JSON::parse(r#"{"type": "Newtype", "a": 1}"#).unwrap();
+    assert_eq!(
+        from_value_with::<Plain>(input.clone(), &strict).unwrap(),
+        Plain::Newtype(btreemap! { "a".to_string() => 1 })
+    );
+    assert_eq!(
+        js_sys::Reflect::get(&input, &"type".into()).unwrap(),
+        "Newtype"
+    );
+    let input = js_sys::JSON::parse(r#"{"type": "Point", "x": 1}"#).unwrap();
+    Object::freeze(input.unchecked_ref::<Object>());
+    assert_eq!(
+        from_value_with::<Shape>(input.clone(), &strict).unwrap(),
+        Shape::Point(Point { x: 1 })
+    );
+    assert_eq!(
+        js_sys::Reflect::get(&input, &"type".into()).unwrap(),
+        "Point"
+    );
+
+    // Internal tags can't be merged with non-object payloads.
+    #[derive(Serialize)]
+    enum Unsupported {
+        Newtype(i32),
+        Tuple(i32, i32),
+    }
+
+    let serializer = Serializer::new().enum_representation(internal);
+    Unsupported::Newtype(1).serialize(&serializer).unwrap_err();
+    Unsupported::Tuple(1, 2).serialize(&serializer).unwrap_err();
+}
+
 #[wasm_bindgen_test]
 fn structs() {
     #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -797,8 +1132,1000 @@ fn maps_objects_object_key() {
     let res = src.serialize(&MAP_OBJECT_SERIALIZER).unwrap_err();
     assert_eq!(
         res.to_string(),
-        Error::custom("Map key is not a string and cannot be an object key").to_string()
+        format!(
+            "{} at [Map key #0]",
+            Error::custom("Map key is not a string and cannot be an object key")
+        )
+    );
+    assert_eq!(res.path(), [PathSegment::MapKey(0)]);
+}
+
+#[wasm_bindgen_test]
+fn error_paths() {
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Item {
+        price: u32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Order {
+        items: Vec<Item>,
+        tags: HashMap<String, u8>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Root {
+        order: Order,
+    }
+
+    let value = js_sys::JSON::parse(
+        r#"{ "order": { "items": [{ "price": 1 }, { "price": "x" }], "tags": {} } }"#,
+    )
+    .unwrap();
+    let err = from_value::<Root>(value).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"invalid type: string "x", expected u32 at order.items[1].price"#
+    );
+    assert_eq!(
+        err.path(),
+        [
+            PathSegment::Field("order".to_string()),
+            PathSegment::Field("items".to_string()),
+            PathSegment::Index(1),
+            PathSegment::Field("price".to_string()),
+        ]
+    );
+    // The path is also exposed on the thrown JS error.
+    let js_err = JsValue::from(err);
+    assert_eq!(
+        js_sys::Reflect::get(&js_err, &"path".into()).unwrap(),
+        "order.items[1].price"
+    );
+
+    // Keys that aren't identifiers are quoted.
+    let value = js_sys::JSON::parse(r#"{ "items": [], "tags": { "a b": 256 } }"#).unwrap();
+    let err = from_value::<Order>(value).unwrap_err();
+    assert_eq!(err.path()[1], PathSegment::Field("a b".to_string()));
+    assert!(err.to_string().ends_with(r#" at tags["a b"]"#));
+
+    // Errors in the root value have no path.
+    let err = from_value::<u32>("x".into()).unwrap_err();
+    assert!(err.path().is_empty());
+
+    // Keys and values of ES2015 maps are identified by the position of the entry.
+    let map = js_sys::Map::new();
+    map.set(&1.into(), &2.into());
+    map.set(&"x".into(), &3.into());
+    let err = from_value::<HashMap<u8, u8>>(map.clone().into()).unwrap_err();
+    assert_eq!(err.path(), [PathSegment::MapKey(1)]);
+    map.delete(&"x".into());
+    map.set(&3.into(), &"x".into());
+    let err = from_value::<HashMap<u8, u8>>(map.into()).unwrap_err();
+    assert_eq!(err.path(), [PathSegment::MapValue(1)]);
+
+    // Serialization errors are reported with a path as well.
+    let serializer = Serializer::new().number_policy(NumberPolicy::Number);
+    let err = vec![btreemap! { "big" => u64::MAX }]
+        .serialize(&serializer)
+        .unwrap_err();
+    assert_eq!(
+        err.path(),
+        [PathSegment::Index(0), PathSegment::Field("big".to_string())]
+    );
+    let err = vec![Some(u64::MAX)].serialize(&serializer).unwrap_err();
+    assert_eq!(err.path(), [PathSegment::Index(0)]);
+}
+
+#[wasm_bindgen_test]
+fn error_kinds() {
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Struct {
+        #[allow(dead_code)]
+        a: u8,
+    }
+
+    #[derive(Debug, Deserialize)]
+    enum Enum {
+        A,
+    }
+
+    let err = from_value::<u32>("x".into()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+    assert_eq!(err.expected(), Some("u32"));
+    assert_eq!(err.received(), Some(r#"string "x""#));
+
+    let err = from_value::<u8>(300.into()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    let err = from_value::<u64>(JsValue::from(-1_i64)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    let err = from_value::<u32>((-1).into()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    assert_eq!(err.received(), Some("integer `-1`"));
+    let err = from_value::<u64>((Number::MAX_SAFE_INTEGER + 1.).into()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    let err = from_value::<u32>(1.5.into()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+
+    // Integer strings that don't fit are out of range as well.
+    let coerce = DeserializerConfig::new().coerce_primitives(true);
+    let err = from_value_with::<u8>("300".into(), &coerce).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    let err = from_value_with::<u32>("-1".into(), &coerce).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    let strings = DeserializerConfig::new().number_policy(NumberPolicy::String);
+    let err = from_value_with::<u64>("18446744073709551616".into(), &strings).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    let err = from_value_with::<u64>("-1".into(), &strings).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    let err = from_value_with::<u64>("1e3".into(), &strings).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidValue);
+
+    let err = from_value::<Struct>(Object::new().into()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingField);
+    assert_eq!(err.expected(), Some("`a`"));
+
+    let value = js_sys::JSON::parse(r#"{ "a": 1, "b": 2 }"#).unwrap();
+    let err = from_value_with::<Struct>(value, &DeserializerConfig::strict()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnknownField);
+    assert_eq!(err.to_string(), "unknown field `b`, expected `a`");
+    assert_eq!(err.received(), Some("`b`"));
+
+    let err = from_value::<Enum>("B".into()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidValue);
+    assert_eq!(err.to_string(), "unknown variant `B`, expected `A`");
+
+    let err = u64::MAX
+        .serialize(&Serializer::new().number_policy(NumberPolicy::Number))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+
+    let err = Error::custom("custom");
+    assert_eq!(err.kind(), ErrorKind::Custom);
+
+    let exception = JsValue::from(js_sys::Error::new("boom"));
+    let err = Error::from(exception.clone());
+    assert_eq!(err.kind(), ErrorKind::JsException);
+    assert_eq!(err.js_exception(), Some(&exception));
+    assert_eq!(err.to_string(), "Error: boom");
+    assert_eq!(JsValue::from(err), exception);
+
+    // Details are copied onto the thrown JS error.
+    let err = JsValue::from(from_value::<u32>("x".into()).unwrap_err());
+    let get = |key: &str| js_sys::Reflect::get(&err, &key.into()).unwrap();
+    assert!(err.is_instance_of::<js_sys::Error>());
+    assert_eq!(get("kind"), "InvalidType");
+    assert_eq!(get("expected"), "u32");
+    assert_eq!(get("received"), r#"string "x""#);
+    assert!(get("path").is_undefined());
+}
+
+#[wasm_bindgen_test]
+fn js_error_classes() {
+    let err = JsValue::from(from_value::<u32>("x".into()).unwrap_err());
+    assert!(err.is_instance_of::<js_sys::TypeError>());
+
+    let err = JsValue::from(from_value::<u64>(JsValue::from(-1_i64)).unwrap_err());
+    assert!(err.is_instance_of::<js_sys::RangeError>());
+    let err = JsValue::from(from_value::<u32>((-1).into()).unwrap_err());
+    assert!(err.is_instance_of::<js_sys::RangeError>());
+    let err = u64::MAX
+        .serialize(&Serializer::new().number_policy(NumberPolicy::Number))
+        .unwrap_err();
+    let err = JsValue::from(err);
+    assert!(err.is_instance_of::<js_sys::RangeError>());
+
+    // Other kinds are plain errors.
+    let err = JsValue::from(from_value::<(u8, u8)>(to_value(&[1]).unwrap()).unwrap_err());
+    assert!(err.is_instance_of::<js_sys::Error>());
+    assert!(!err.is_instance_of::<js_sys::TypeError>());
+    assert!(!err.is_instance_of::<js_sys::RangeError>());
+}
+
+#[wasm_bindgen_test]
+fn deserializer_config() {
+    let strict = DeserializerConfig::strict();
+
+    // `null` is only treated as a missing value when allowed by the config,
+    // and the config reaches values nested in arrays, objects and maps.
+    from_value_with::<Option<u32>>(JsValue::NULL, &strict).unwrap_err();
+    from_value_with::<Vec<Option<u32>>>(Array::of1(&JsValue::NULL).into(), &strict).unwrap_err();
+    assert_eq!(
+        from_value_with::<Vec<Option<u32>>>(
+            Array::of1(&JsValue::NULL).into(),
+            &DeserializerConfig::json_compatible()
+        )
+        .unwrap(),
+        vec![None]
+    );
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Struct {
+        value: Option<u32>,
+    }
+
+    let obj = js_sys::JSON::parse(r#"{"value": null}"#).unwrap();
+    assert_eq!(
+        from_value_with::<Struct>(obj.clone(), &DeserializerConfig::new()).unwrap(),
+        Struct { value: None }
+    );
+    from_value_with::<Struct>(obj, &strict).unwrap_err();
+    assert_eq!(
+        from_value_with::<Struct>(Object::new().into(), &strict).unwrap(),
+        Struct { value: None }
+    );
+
+    let map = js_sys::Map::new().set(&"a".into(), &JsValue::NULL);
+    from_value_with::<HashMap<String, Option<u32>>>(map.into(), &strict).unwrap_err();
+
+    // Bytes from plain arrays can be disabled as well.
+    let value = to_value(&[1, 2, 3]).unwrap();
+    from_value::<serde_bytes::ByteBuf>(value.clone()).unwrap();
+    from_value_with::<serde_bytes::ByteBuf>(value, &strict).unwrap_err();
+
+    // The JSON preset treats `undefined` as missing and rejects values `JSON.parse` can't produce.
+    let json = DeserializerConfig::json_compatible();
+    assert_eq!(
+        from_value_with::<Option<u32>>(JsValue::UNDEFINED, &json).unwrap(),
+        None
+    );
+    let obj = js_sys::JSON::parse(r#"{"value": 1}"#).unwrap();
+    assert_eq!(
+        from_value_with::<HashMap<String, u32>>(obj.clone(), &json).unwrap(),
+        hashmap! { "value".to_owned() => 1 }
+    );
+    assert_eq!(
+        from_value_with::<Struct>(obj, &json).unwrap(),
+        Struct { value: Some(1) }
+    );
+    let map = js_sys::Map::new().set(&"value".into(), &1.into());
+    from_value::<HashMap<String, u32>>(map.clone().into()).unwrap();
+    let err = from_value_with::<HashMap<String, u32>>(map.into(), &json).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+    let set = js_sys::Set::new(&Array::of1(&1.into()));
+    from_value_with::<Vec<u32>>(set.into(), &json).unwrap_err();
+    from_value_with::<u64>(BigInt::from(1).into(), &json).unwrap_err();
+    let bytes = js_sys::Uint8Array::from(&[1, 2, 3][..]);
+    from_value_with::<serde_bytes::ByteBuf>(bytes.into(), &json).unwrap_err();
+    assert_eq!(
+        from_value_with::<serde_bytes::ByteBuf>(to_value(&[1, 2, 3]).unwrap(), &json).unwrap(),
+        serde_bytes::ByteBuf::from(vec![1, 2, 3])
+    );
+    from_value_with::<HashMap<String, u32>>(Array::new().into(), &json).unwrap_err();
+}
+
+#[wasm_bindgen_test]
+fn unknown_fields() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Strict {
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Lax {
+        name: String,
+    }
+
+    let obj = js_sys::JSON::parse(r#"{"name": "x", "nmae": "y"}"#).unwrap();
+
+    // By default unknown properties are never looked at, even with `deny_unknown_fields`.
+    from_value::<Strict>(obj.clone()).unwrap();
+
+    let err = from_value_with::<Strict>(obj.clone(), &DeserializerConfig::strict()).unwrap_err();
+    assert!(err.to_string().contains("unknown field `nmae`"), "{}", err);
+    // Types without `deny_unknown_fields` still ignore them.
+    assert_eq!(
+        from_value_with::<Lax>(obj.clone(), &DeserializerConfig::strict()).unwrap(),
+        Lax {
+            name: "x".to_string()
+        }
+    );
+
+    // Warnings can be collected by the callback, together with the path of each property.
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Outer {
+        items: Vec<Lax>,
+        tagged: HashMap<String, Lax>,
+    }
+
+    let reported = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let config = DeserializerConfig::new().unknown_fields(UnknownFields::Warn({
+        let reported = reported.clone();
+        std::rc::Rc::new(move |path: &[PathSegment]| reported.borrow_mut().push(path.to_vec()))
+    }));
+    from_value_with::<Strict>(obj, &config).unwrap();
+    assert_eq!(
+        *reported.borrow(),
+        [[PathSegment::Field("nmae".to_owned())]]
+    );
+
+    let obj = js_sys::JSON::parse(
+        r#"{"items": [{"name": "x"}, {"name": "y", "extra": 1}], "tagged": {"a": {"name": "z", "more": 2}}}"#,
+    )
+    .unwrap();
+    reported.borrow_mut().clear();
+    from_value_with::<Outer>(obj, &config).unwrap();
+    assert_eq!(
+        *reported.borrow(),
+        [
+            vec![
+                PathSegment::Field("items".to_owned()),
+                PathSegment::Index(1),
+                PathSegment::Field("extra".to_owned())
+            ],
+            vec![
+                PathSegment::Field("tagged".to_owned()),
+                PathSegment::Field("a".to_owned()),
+                PathSegment::Field("more".to_owned())
+            ],
+        ]
+    );
+}
+
+#[wasm_bindgen_test]
+fn cycles_and_depth_limit() {
+    #[derive(Debug, Deserialize)]
+    struct Tree {
+        #[allow(dead_code)]
+        children: Vec<Tree>,
+    }
+
+    fn parse(depth: usize) -> JsValue {
+        let json = format!(
+            "{}{}",
+            r#"{"children": ["#.repeat(depth),
+            "]}".repeat(depth)
+        );
+        js_sys::JSON::parse(&json).unwrap()
+    }
+
+    // The same object may appear several times as long as it doesn't contain itself.
+    let shared = parse(1);
+    let obj = Array::of2(&shared, &shared);
+    from_value::<Vec<Tree>>(obj.into()).unwrap();
+
+    let obj = parse(2);
+    let child = Array::from(&js_sys::Reflect::get(&obj, &"children".into()).unwrap()).get(0);
+    Array::from(&js_sys::Reflect::get(&child, &"children".into()).unwrap()).push(&obj);
+    let err = from_value::<Tree>(obj.clone()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Cycle);
+    // Cycles are only tracked from a depth of 32, so the error points at the first
+    // repeated reference past that depth.
+    let segments = [
+        PathSegment::Field("children".to_owned()),
+        PathSegment::Index(0),
+    ];
+    let path = segments
+        .iter()
+        .cycle()
+        .take(36)
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(err.path(), path);
+    let unlimited = DeserializerConfig::new().max_depth(None);
+    let result = from_value_with::<Tree>(obj.clone(), &unlimited);
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Cycle);
+    let shallow = DeserializerConfig::new().max_depth(Some(4));
+    let result = from_value_with::<Tree>(obj.clone(), &shallow);
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::DepthLimit);
+    #[cfg(feature = "batch")]
+    {
+        let batched =
+            serde_wasm_bindgen::from_value_batched::<Tree>(obj, &DeserializerConfig::new());
+        let batched = batched.unwrap_err();
+        assert_eq!(batched.kind(), ErrorKind::Cycle);
+        assert_eq!(batched.path(), err.path());
+    }
+
+    // Each level of the tree is an object and an array.
+    let obj = parse(100);
+    let err = from_value::<Tree>(obj.clone()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DepthLimit);
+    assert_eq!(err.path().len(), 128);
+    from_value_with::<Tree>(obj, &DeserializerConfig::new().max_depth(None)).unwrap();
+    let config = DeserializerConfig::new().max_depth(Some(4));
+    from_value_with::<Tree>(parse(2), &config).unwrap();
+    let err = from_value_with::<Tree>(parse(3), &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DepthLimit);
+}
+
+#[wasm_bindgen_test]
+fn resource_limits() {
+    fn limit_exceeded<T: DeserializeOwned + Debug>(value: JsValue, config: &DeserializerConfig) {
+        let err = from_value_with::<T>(value.clone(), config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded, "{}", err);
+        #[cfg(feature = "batch")]
+        {
+            let err = serde_wasm_bindgen::from_value_batched::<T>(value, config).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::LimitExceeded, "{}", err);
+        }
+    }
+
+    let array = to_value(&[1, 2, 3]).unwrap();
+    let config = DeserializerConfig::new().max_sequence_length(Some(3));
+    from_value_with::<Vec<u8>>(array.clone(), &config).unwrap();
+    limit_exceeded::<Vec<u8>>(array.clone(), &config.clone().max_sequence_length(Some(2)));
+    let set = js_sys::Set::new(&array);
+    limit_exceeded::<Vec<u8>>(set.into(), &config.clone().max_sequence_length(Some(2)));
+    let floats = js_sys::Float64Array::new_with_length(3);
+    #[derive(Debug, Deserialize)]
+    struct Floats(#[serde(with = "serde_wasm_bindgen::typed_array")] Vec<f64>);
+    let config = config.max_sequence_length(Some(2));
+    let Floats(within) =
+        from_value_with(js_sys::Float64Array::new_with_length(2).into(), &config).unwrap();
+    assert_eq!(within, [0.0, 0.0]);
+    limit_exceeded::<Floats>(floats.into(), &config);
+
+    let config = DeserializerConfig::new().max_bytes_length(Some(2));
+    limit_exceeded::<serde_bytes::ByteBuf>(array.clone(), &config);
+    let bytes = js_sys::Uint8Array::from(&[1, 2, 3][..]);
+    limit_exceeded::<serde_bytes::ByteBuf>(bytes.into(), &config);
+
+    let config = DeserializerConfig::new().max_string_length(Some(3));
+    from_value_with::<String>("abc".into(), &config).unwrap();
+    limit_exceeded::<String>("abcd".into(), &config);
+    limit_exceeded::<u32>("1234".into(), &config.coerce_primitives(true));
+
+    let obj = js_sys::JSON::parse(r#"{"a": 1, "b": 2, "c": 3}"#).unwrap();
+    let config = DeserializerConfig::new().max_map_entries(Some(2));
+    limit_exceeded::<HashMap<String, u8>>(obj.clone(), &config);
+    let map = js_sys::Map::new()
+        .set(&1.into(), &2.into())
+        .set(&3.into(), &4.into())
+        .set(&5.into(), &6.into());
+    limit_exceeded::<HashMap<u8, u8>>(map.into(), &config);
+
+    // Keys and values both count as values.
+    let config = DeserializerConfig::new().max_nodes(Some(6));
+    from_value_with::<HashMap<String, u8>>(obj.clone(), &config).unwrap();
+    limit_exceeded::<HashMap<String, u8>>(obj, &config.clone().max_nodes(Some(5)));
+    let nested = to_value(&[[1, 2], [3, 4]]).unwrap();
+    limit_exceeded::<Vec<Vec<u8>>>(nested, &config.max_nodes(Some(5)));
+}
+
+#[wasm_bindgen_test]
+fn prototype_pollution() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct User {
+        name: String,
+        admin: Option<bool>,
+    }
+
+    type Nested = HashMap<String, HashMap<String, bool>>;
+
+    fn check(value: JsValue, expected: &Nested, null_prototype: bool) {
+        let proto = Object::get_prototype_of(&value);
+        if null_prototype {
+            assert!(proto.is_null());
+        } else {
+            assert_eq!(proto, Object::get_prototype_of(&Object::new()));
+        }
+        assert_eq!(
+            Object::keys(value.unchecked_ref::<Object>()).to_vec(),
+            ["__proto__"]
+        );
+        assert_eq!(&from_value::<Nested>(value.clone()).unwrap(), expected);
+        // The key didn't replace the prototype, so the user is still not an admin.
+        js_sys::Reflect::set(&value, &"name".into(), &"x".into()).unwrap();
+        assert_eq!(from_value::<User>(value).unwrap().admin, None);
+    }
+
+    let map = hashmap! { "__proto__".to_owned() => hashmap! { "admin".to_owned() => true } };
+    let null_prototype = MAP_OBJECT_SERIALIZER.null_prototype_objects(true);
+    check(map.serialize(&MAP_OBJECT_SERIALIZER).unwrap(), &map, false);
+    check(map.serialize(&null_prototype).unwrap(), &map, true);
+    #[cfg(feature = "batch")]
+    {
+        check(
+            MAP_OBJECT_SERIALIZER.serialize_batched(&map).unwrap(),
+            &map,
+            false,
+        );
+        check(null_prototype.serialize_batched(&map).unwrap(), &map, true);
+    }
+
+    // Existing objects keep their prototype, even if new objects wouldn't have one.
+    let target = JsValue::from(Object::new());
+    null_prototype.serialize_into(&map, &target).unwrap();
+    check(target, &map, false);
+
+    // Inherited properties are ignored on the deserializer side as well.
+    let proto = js_sys::JSON::parse(r#"{"admin": true, "type": "Admin"}"#).unwrap();
+    let obj = Object::create(proto.unchecked_ref::<Object>());
+    js_sys::Reflect::set(&obj, &"name".into(), &"x".into()).unwrap();
+    assert_eq!(
+        from_value::<User>(obj.clone().into()).unwrap(),
+        User {
+            name: "x".to_owned(),
+            admin: None,
+        }
+    );
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    enum Role {
+        Admin,
+        User,
+    }
+
+    let config =
+        DeserializerConfig::new().enum_representation(EnumRepresentation::Internal { tag: "type" });
+    let err = from_value_with::<Role>(obj.into(), &config).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingField);
+
+    // Getters of class instances are inherited too, but still read.
+    let class = js_sys::Function::new_no_args(
+        "return class { constructor() { this.id = 1; } get name() { return 'x'; } get type() { return 'User'; } }",
+    )
+    .call0(&JsValue::UNDEFINED)
+    .unwrap();
+    let instance = js_sys::Reflect::construct(
+        class.unchecked_ref::<js_sys::Function>(),
+        &js_sys::Array::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        from_value::<User>(instance.clone()).unwrap(),
+        User {
+            name: "x".to_owned(),
+            admin: None,
+        }
+    );
+    assert!(matches!(
+        from_value_with::<Role>(instance, &config).unwrap(),
+        Role::User
+    ));
+}
+
+#[wasm_bindgen_test]
+fn freeze_mode() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Payload {
+        list: Vec<u32>,
+        map: HashMap<String, u32>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Message {
+        Data(Payload),
+    }
+
+    fn get(value: &JsValue, key: &str) -> JsValue {
+        js_sys::Reflect::get(value, &key.into()).unwrap()
+    }
+
+    fn is_frozen(value: &JsValue) -> bool {
+        Object::is_frozen(value.unchecked_ref::<Object>())
+    }
+
+    fn check(value: JsValue, expected: &Message, throws: bool) {
+        assert!(is_frozen(&value));
+        assert_eq!(get(&value, "type"), "Data");
+        let list = get(&value, "list");
+        let map = get(&value, "map");
+        assert!(is_frozen(&list) && is_frozen(&map));
+        assert_eq!(
+            &from_value_with::<Message>(value.clone(), &INTERNAL_CONFIG).unwrap(),
+            expected
+        );
+
+        let set = js_sys::Reflect::set(&list, &0.into(), &10.into());
+        let map_set = get(&map, "set").unchecked_into::<js_sys::Function>().call2(
+            &map,
+            &"b".into(),
+            &2.into(),
+        );
+        if throws {
+            assert!(set.is_err());
+            assert!(map_set.is_err());
+        } else {
+            assert_eq!(set, Ok(false));
+            // `Object.freeze` doesn't cover `Map` entries.
+            assert!(map_set.is_ok());
+        }
+        assert_eq!(get(&list, "0"), 1);
+    }
+
+    const INTERNAL: Serializer =
+        Serializer::new().enum_representation(EnumRepresentation::Internal { tag: "type" });
+    const INTERNAL_CONFIG: DeserializerConfig =
+        DeserializerConfig::new().enum_representation(EnumRepresentation::Internal { tag: "type" });
+
+    let value = Message::Data(Payload {
+        list: vec![1, 2],
+        map: hashmap! { "a".to_owned() => 1 },
+    });
+    let serializers = [
+        (INTERNAL.freeze(FreezeMode::Freeze), false),
+        (INTERNAL.freeze(FreezeMode::Throw), cfg!(debug_assertions)),
+    ];
+    for (serializer, throws) in serializers.iter() {
+        check(value.serialize(serializer).unwrap(), &value, *throws);
+        #[cfg(feature = "batch")]
+        check(
+            serializer.serialize_batched(&value).unwrap(),
+            &value,
+            *throws,
+        );
+    }
+
+    // Values stay mutable by default.
+    let value = value.serialize(&INTERNAL).unwrap();
+    assert!(!is_frozen(&value) && !is_frozen(&get(&value, "list")));
+}
+
+#[wasm_bindgen_test]
+fn diff_serialization() {
+    #[derive(Serialize, Clone)]
+    struct Item {
+        id: u32,
+        tags: Vec<String>,
+    }
+
+    #[derive(Serialize, Clone)]
+    enum Mode {
+        Idle,
+        Editing { item: u32 },
+        Viewing(Item),
+    }
+
+    #[derive(Serialize, Clone)]
+    struct State {
+        items: Vec<Item>,
+        lookup: BTreeMap<String, Item>,
+        mode: Mode,
+        #[serde(with = "serde_bytes")]
+        bytes: Vec<u8>,
+    }
+
+    fn get(value: &JsValue, key: &str) -> JsValue {
+        js_sys::Reflect::get(value, &key.into()).unwrap()
+    }
+
+    fn same(a: &JsValue, b: &JsValue) -> bool {
+        Object::is(a, b)
+    }
+
+    let item = |id| Item {
+        id,
+        tags: vec!["a".to_owned()],
+    };
+    let state = State {
+        items: vec![item(1), item(2)],
+        lookup: btreemap! { "x".to_owned() => item(3) },
+        mode: Mode::Editing { item: 1 },
+        bytes: vec![1, 2, 3],
+    };
+
+    for serializer in [
+        Serializer::new(),
+        Serializer::json_compatible(),
+        Serializer::new().enum_representation(EnumRepresentation::Internal { tag: "type" }),
+        Serializer::new().enum_representation(EnumRepresentation::Adjacent {
+            tag: "type",
+            content: "value",
+        }),
+    ]
+    .iter()
+    {
+        let first = serializer
+            .serialize_diff(&state, &JsValue::UNDEFINED)
+            .unwrap();
+        let second = serializer.serialize_diff(&state, &first).unwrap();
+        assert!(same(&first, &second));
+
+        // Only the path to the changed value is rebuilt.
+        let mut changed = state.clone();
+        changed.items[1].tags.push("b".to_owned());
+        let third = serializer.serialize_diff(&changed, &second).unwrap();
+        assert!(!same(&third, &second));
+        assert!(!same(&get(&third, "items"), &get(&second, "items")));
+        let items = get(&third, "items");
+        assert!(same(&get(&items, "0"), &get(&get(&second, "items"), "0")));
+        assert!(same(&get(&third, "lookup"), &get(&second, "lookup")));
+        assert!(same(&get(&third, "mode"), &get(&second, "mode")));
+        assert!(same(&get(&third, "bytes"), &get(&second, "bytes")));
+        assert_eq!(
+            js_sys::JSON::stringify(&third).unwrap(),
+            js_sys::JSON::stringify(&changed.serialize(serializer).unwrap()).unwrap()
+        );
+
+        // Variants are compared as well.
+        for mode in [Mode::Idle, Mode::Viewing(item(4))].iter() {
+            let state = State {
+                mode: mode.clone(),
+                ..changed.clone()
+            };
+            let fourth = serializer.serialize_diff(&state, &third).unwrap();
+            assert!(!same(&get(&fourth, "mode"), &get(&third, "mode")));
+            let fifth = serializer.serialize_diff(&state, &fourth).unwrap();
+            assert!(same(&fifth, &fourth));
+        }
+    }
+
+    // Previous values of a different shape are never reused.
+    let extra = js_sys::JSON::parse(r#"{"id": 1, "tags": ["a"], "extra": true}"#).unwrap();
+    let value = serde_wasm_bindgen::to_value_diff(&item(1), &extra).unwrap();
+    assert!(!same(&value, &extra));
+    assert!(same(&get(&value, "tags"), &get(&extra, "tags")));
+}
+
+#[wasm_bindgen_test]
+fn serialize_into_existing() {
+    #[derive(Serialize)]
+    struct Config {
+        name: &'static str,
+        size: u32,
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { radius: u32 },
+    }
+
+    fn json(value: &JsValue) -> String {
+        js_sys::JSON::stringify(value).unwrap().into()
+    }
+
+    let config = Config { name: "a", size: 1 };
+    let target = js_sys::JSON::parse(r#"{"size": 0, "extra": true}"#).unwrap();
+    serde_wasm_bindgen::to_value_into(&config, &target).unwrap();
+    assert_eq!(json(&target), r#"{"size":1,"extra":true,"name":"a"}"#);
+    let pruning = Serializer::new().remove_stale_keys(true);
+    pruning.serialize_into(&config, &target).unwrap();
+    assert_eq!(json(&target), r#"{"size":1,"name":"a"}"#);
+
+    let target = js_sys::JSON::parse("[9, 9, 9]").unwrap();
+    SERIALIZER.serialize_into(&[1, 2], &target).unwrap();
+    assert_eq!(json(&target), "[1,2,9]");
+    SERIALIZER.serialize_into(&[1, 2, 3, 4], &target).unwrap();
+    assert_eq!(json(&target), "[1,2,3,4]");
+    pruning.serialize_into(&[5], &target).unwrap();
+    assert_eq!(json(&target), "[5]");
+
+    let target = js_sys::Map::new();
+    target.set(&"stale".into(), &JsValue::TRUE);
+    pruning
+        .serialize_into(&btreemap! { "a" => 1 }, &target)
+        .unwrap();
+    assert_eq!(target.size(), 1);
+    assert_eq!(target.get(&"a".into()), 1);
+
+    let target = Object::new().into();
+    let internal =
+        Serializer::new().enum_representation(EnumRepresentation::Internal { tag: "type" });
+    internal
+        .serialize_into(&Shape::Circle { radius: 2 }, &target)
+        .unwrap();
+    assert_eq!(json(&target), r#"{"type":"Circle","radius":2}"#);
+    SERIALIZER
+        .serialize_into(&Shape::Circle { radius: 3 }, &target)
+        .unwrap();
+    assert_eq!(
+        json(&target),
+        r#"{"type":"Circle","radius":2,"Circle":{"radius":3}}"#
+    );
+
+    let err = SERIALIZER.serialize_into(&[1], &target).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+    let err = SERIALIZER.serialize_into(&1, &target).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+}
+
+#[wasm_bindgen_test]
+fn deserialize_in_place() {
+    let mut place = vec![String::with_capacity(64), String::new(), String::new()];
+    let ptr = place[0].as_ptr();
+    let value = to_value(&["abc", "def"]).unwrap();
+    serde_wasm_bindgen::from_value_in_place(&mut place, value).unwrap();
+    assert_eq!(place, ["abc", "def"]);
+    // The existing buffer was reused instead of replaced.
+    assert_eq!(place[0].as_ptr(), ptr);
+    assert_eq!(place[0].capacity(), 64);
+
+    let mut place = (vec![1u32, 2, 3], String::from("old"));
+    let value = to_value(&(vec![4u32, 5, 6, 7], "é\u{1F600}")).unwrap();
+    serde_wasm_bindgen::from_value_in_place(&mut place, value).unwrap();
+    assert_eq!(place, (vec![4, 5, 6, 7], "é\u{1F600}".to_owned()));
+
+    let config = DeserializerConfig::new().max_string_length(Some(2));
+    let err = serde_wasm_bindgen::from_value_in_place_with(
+        &mut place.1,
+        JsValue::from_str("long"),
+        &config,
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+}
+
+#[wasm_bindgen_test]
+fn merge_partial_update() {
+    // Neither type implements `Serialize` or `Deserialize`, and unlisted fields are kept as is.
+    #[derive(Debug, PartialEq, Clone)]
+    struct Settings {
+        theme: String,
+        accent: Option<String>,
+        cache: Vec<u32>,
+    }
+
+    impl Merge for Settings {
+        fn merge_from(&mut self, patch: Patch) -> Result<(), Error> {
+            patch.merge_fields(&["theme", "accent"], |field, patch| match field {
+                "theme" => self.theme.merge_from(patch),
+                "accent" => self.accent.merge_from(patch),
+                _ => Ok(()),
+            })
+        }
+    }
+
+    #[derive(Debug, PartialEq, Clone)]
+    struct App {
+        settings: Settings,
+        labels: BTreeMap<u32, String>,
+        recent: Vec<u32>,
+        session: u64,
+    }
+
+    impl Merge for App {
+        fn merge_from(&mut self, patch: Patch) -> Result<(), Error> {
+            patch.merge_fields(
+                &["settings", "labels", "recent"],
+                |field, patch| match field {
+                    "settings" => self.settings.merge_from(patch),
+                    "labels" => self.labels.merge_from(patch),
+                    "recent" => self.recent.merge_from(patch),
+                    _ => Ok(()),
+                },
+            )
+        }
+    }
+
+    let mut app = App {
+        settings: Settings {
+            theme: String::with_capacity(64),
+            accent: Some("blue".to_owned()),
+            cache: vec![1, 2],
+        },
+        labels: btreemap! { 1 => "one".to_owned(), 3 => "three".to_owned() },
+        recent: vec![1, 2, 3],
+        session: 42,
+    };
+    let theme_ptr = app.settings.theme.as_ptr();
+
+    let patch = js_sys::JSON::parse(
+        r#"{"settings": {"theme": "dark"}, "labels": {"1": "uno", "2": "deux"}, "recent": [4]}"#,
+    )
+    .unwrap();
+    serde_wasm_bindgen::merge_from_value(&mut app, patch).unwrap();
+    assert_eq!(
+        app,
+        App {
+            settings: Settings {
+                theme: "dark".to_owned(),
+                accent: Some("blue".to_owned()),
+                cache: vec![1, 2],
+            },
+            labels: btreemap! {
+                1 => "uno".to_owned(),
+                2 => "deux".to_owned(),
+                3 => "three".to_owned(),
+            },
+            recent: vec![4],
+            session: 42,
+        }
+    );
+    // The nested struct was updated in place instead of replaced.
+    assert_eq!(app.settings.theme.as_ptr(), theme_ptr);
+
+    // A mismatching property reports its path and leaves the value untouched.
+    let before = app.clone();
+    let patch = js_sys::JSON::parse(r#"{"settings": {"theme": 1}}"#).unwrap();
+    let err = serde_wasm_bindgen::merge_from_value(&mut app, patch).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+    assert_eq!(
+        err.path(),
+        [
+            PathSegment::Field("settings".to_owned()),
+            PathSegment::Field("theme".to_owned())
+        ]
+    );
+    assert_eq!(app, before);
+
+    // Missing fields are left untouched whatever their type, while `null` clears an `Option`.
+    let patch = js_sys::JSON::parse(r#"{"recent": []}"#).unwrap();
+    serde_wasm_bindgen::merge_from_value(&mut app, patch).unwrap();
+    assert_eq!(
+        app,
+        App {
+            recent: vec![],
+            ..before.clone()
+        }
+    );
+    let patch = js_sys::JSON::parse(r#"{"settings": {"accent": null}}"#).unwrap();
+    serde_wasm_bindgen::merge_from_value(&mut app, patch).unwrap();
+    assert_eq!(app.settings.accent, None);
+    assert_eq!(app.settings.theme, "dark");
+
+    // Unknown properties such as `__proto__` are ignored.
+    let patch =
+        js_sys::JSON::parse(r#"{"settings": {"theme": "light", "__proto__": {"polluted": true}}}"#)
+            .unwrap();
+    serde_wasm_bindgen::merge_from_value(&mut app, patch).unwrap();
+    assert!(!js_sys::Reflect::has(&Object::new(), &"polluted".into()).unwrap());
+    assert_eq!(app.settings.theme, "light");
+    assert_eq!(app.session, 42);
+}
+
+#[wasm_bindgen_test]
+fn map_keys_from_object_properties() {
+    fn parse<T: DeserializeOwned>(json: &str) -> Result<T, Error> {
+        from_value(js_sys::JSON::parse(json).unwrap())
+    }
+
+    assert_eq!(
+        parse::<HashMap<u32, String>>(r#"{"1": "a", "2": "b"}"#).unwrap(),
+        hashmap! { 1 => "a".to_owned(), 2 => "b".to_owned() }
+    );
+    assert_eq!(
+        parse::<BTreeMap<i64, u8>>(r#"{"-3": 1, "7": 2}"#).unwrap(),
+        btreemap! { -3 => 1, 7 => 2 }
+    );
+    #[derive(Debug, Deserialize, PartialEq, Eq, Hash)]
+    struct Id(u16);
+
+    assert_eq!(
+        parse::<HashMap<Id, u8>>(r#"{"10": 1}"#).unwrap(),
+        hashmap! { Id(10) => 1 }
     );
+    assert_eq!(
+        parse::<BTreeMap<bool, u8>>(r#"{"true": 1, "false": 0}"#).unwrap(),
+        btreemap! { false => 0, true => 1 }
+    );
+    assert_eq!(
+        parse::<BTreeMap<char, u8>>(r#"{"a": 1}"#).unwrap(),
+        btreemap! { 'a' => 1 }
+    );
+    assert_eq!(
+        parse::<BTreeMap<Option<u32>, u8>>(r#"{"5": 1}"#).unwrap(),
+        btreemap! { Some(5) => 1 }
+    );
+
+    // Maps are still deserialized from their own keys.
+    let map = js_sys::Map::new();
+    map.set(&1.into(), &"a".into());
+    assert_eq!(
+        from_value::<HashMap<u32, String>>(map.into()).unwrap(),
+        hashmap! { 1 => "a".to_owned() }
+    );
+
+    let err = parse::<HashMap<u32, u8>>(r#"{"x": 1}"#).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+    assert_eq!(err.expected(), Some("u32"));
+    assert_eq!(err.path(), [PathSegment::MapKey(0)]);
+
+    #[cfg(feature = "batch")]
+    {
+        let value = js_sys::JSON::parse(r#"{"1": "a", "2": "b"}"#).unwrap();
+        assert_eq!(
+            serde_wasm_bindgen::from_value_batched::<HashMap<u32, String>>(
+                value,
+                &DeserializerConfig::new()
+            )
+            .unwrap(),
+            hashmap! { 1 => "a".to_owned(), 2 => "b".to_owned() }
+        );
+        let value = js_sys::JSON::parse(r#"{"5": 1}"#).unwrap();
+        assert_eq!(
+            serde_wasm_bindgen::from_value_batched::<BTreeMap<Option<u32>, u8>>(
+                value,
+                &DeserializerConfig::new()
+            )
+            .unwrap(),
+            btreemap! { Some(5) => 1 }
+        );
+    }
 }
 
 #[wasm_bindgen_test]
@@ -836,3 +2163,448 @@ fn preserve_js_value() {
     // panic!("####### HELLO ####### {:?}", output);
     assert!(output.my_value.0.is_truthy());
 }
+
+#[wasm_bindgen_test]
+fn preserve_js_value_nested() {
+    #[derive(Serialize, Deserialize)]
+    enum Wrapper {
+        Newtype(PreserveJsValue<JsValue>),
+        Struct { value: PreserveJsValue<Object> },
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Test {
+        number: i64,
+        optional: Option<PreserveJsValue<JsValue>>,
+        list: Vec<PreserveJsValue<JsValue>>,
+        map: BTreeMap<String, PreserveJsValue<JsValue>>,
+        wrappers: Vec<Wrapper>,
+    }
+
+    let obj = Object::new();
+    let obj_value = JsValue::from(&obj);
+    let input = Test {
+        number: 42,
+        optional: Some(PreserveJsValue(obj.clone().into())),
+        list: vec![
+            PreserveJsValue(JsValue::from("a")),
+            PreserveJsValue(obj.clone().into()),
+        ],
+        map: btreemap! { "key".to_string() => PreserveJsValue(JsValue::NULL) },
+        wrappers: vec![
+            Wrapper::Newtype(PreserveJsValue(obj.clone().into())),
+            Wrapper::Struct {
+                value: PreserveJsValue(obj.clone()),
+            },
+        ],
+    };
+    let js = to_value(&input).unwrap();
+    let output: Test = from_value(js).unwrap();
+
+    assert_eq!(output.number, 42);
+    assert_eq!(output.optional.unwrap().0, obj_value);
+    assert_eq!(output.list[0].0, "a");
+    assert_eq!(output.list[1].0, obj_value);
+    assert!(output.map["key"].0.is_null());
+    match &output.wrappers[..] {
+        [Wrapper::Newtype(a), Wrapper::Struct { value: b }] => {
+            assert_eq!(a.0, obj_value);
+            assert_eq!(b.0, obj);
+        }
+        _ => panic!("unexpected variants"),
+    }
+
+    // Plain integers are not affected by preserved values.
+    assert_eq!(to_value(&1_i64).unwrap(), 1);
+    assert_eq!(from_value::<i64>(1.into()).unwrap(), 1);
+}
+
+#[wasm_bindgen_test]
+fn preserve_adapters() {
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Test {
+        #[serde(with = "serde_wasm_bindgen::preserve")]
+        date: js_sys::Date,
+        #[serde(with = "serde_wasm_bindgen::preserve")]
+        raw: JsValue,
+        #[serde(with = "serde_wasm_bindgen::preserve::option")]
+        callback: Option<js_sys::Function>,
+        #[serde(with = "serde_wasm_bindgen::preserve::vec")]
+        arrays: Vec<Array>,
+        #[serde(with = "serde_wasm_bindgen::preserve::map")]
+        objects: HashMap<String, Object>,
+    }
+
+    let date = js_sys::Date::new_0();
+    let array = Array::of1(&1.into());
+    let obj = Object::new();
+    let input = Test {
+        date: date.clone(),
+        raw: JsValue::from("raw"),
+        callback: None,
+        arrays: vec![array.clone()],
+        objects: hashmap! { "a".to_string() => obj.clone() },
+    };
+    let js = to_value(&input).unwrap();
+    let get = |key: &str| js_sys::Reflect::get(&js, &key.into()).unwrap();
+    assert_eq!(get("date"), JsValue::from(&date));
+
+    let output: Test = from_value(js.clone()).unwrap();
+    assert_eq!(JsValue::from(output.date), JsValue::from(&date));
+    assert_eq!(output.raw, "raw");
+    assert!(output.callback.is_none());
+    assert_eq!(JsValue::from(&output.arrays[0]), JsValue::from(&array));
+    assert_eq!(JsValue::from(&output.objects["a"]), JsValue::from(&obj));
+
+    // Values of a wrong class are rejected with a type error.
+    js_sys::Reflect::set(&js, &"callback".into(), &"not a function".into()).unwrap();
+    let err = from_value::<Test>(js.clone()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+    assert_eq!(err.path(), [PathSegment::Field("callback".to_string())]);
+    assert_eq!(err.expected(), Some("an instance of Function"));
+    assert_eq!(err.received(), Some("string"));
+
+    js_sys::Reflect::set(&js, &"callback".into(), &JsValue::UNDEFINED).unwrap();
+    js_sys::Reflect::set(&js, &"date".into(), &array).unwrap();
+    let err = from_value::<Test>(js).unwrap_err();
+    assert_eq!(err.received(), Some("object Array"));
+}
+
+#[wasm_bindgen_test]
+fn preserve_js_value_other_formats() {
+    #[derive(Serialize, Deserialize)]
+    struct Test {
+        value: PreserveJsValue<JsValue>,
+        #[serde(with = "serde_wasm_bindgen::preserve")]
+        array: Array,
+    }
+
+    let value = js_sys::JSON::parse(r#"{"a":[1,2.5,"x",null,true],"b":{"c":{}}}"#).unwrap();
+    let array = Array::of2(&1.into(), &js_sys::Date::new(&0.into()));
+    let input = Test {
+        value: PreserveJsValue(value),
+        array,
+    };
+
+    // Other serializers see the structure of the JS values instead of a placeholder.
+    let json = serde_json::to_string(&input).unwrap();
+    assert_eq!(
+        json,
+        r#"{"value":{"a":[1,2.5,"x",null,true],"b":{"c":{}}},"array":[1,"1970-01-01T00:00:00.000Z"]}"#
+    );
+
+    // Other deserializers rebuild the JS values.
+    let output: Test = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        js_sys::JSON::stringify(&output.value.0).unwrap(),
+        r#"{"a":[1,2.5,"x",null,true],"b":{"c":{}}}"#
+    );
+    assert_eq!(output.array.length(), 2);
+
+    // The checked adapters still validate the rebuilt values.
+    assert!(serde_json::from_str::<Test>(r#"{"value":null,"array":{}}"#).is_err());
+
+    // Values without a structural representation are rejected instead of being lost.
+    let input = Test {
+        value: PreserveJsValue(js_sys::Function::new_no_args("").into()),
+        array: Array::new(),
+    };
+    assert!(serde_json::to_string(&input).is_err());
+}
+
+#[wasm_bindgen_test]
+fn preserve_js_value_buffered() {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Untagged {
+        Value { value: PreserveJsValue<JsValue> },
+    }
+
+    #[derive(Deserialize)]
+    struct Inner {
+        value: PreserveJsValue<JsValue>,
+    }
+
+    #[derive(Deserialize)]
+    struct Flattened {
+        id: u32,
+        #[serde(flatten)]
+        inner: Inner,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum Checked {
+        Date {
+            #[serde(rename = "value", with = "serde_wasm_bindgen::preserve")]
+            _value: js_sys::Date,
+        },
+    }
+
+    let input = |value: &JsValue| {
+        let obj = Object::new();
+        js_sys::Reflect::set(&obj, &"id".into(), &1.into()).unwrap();
+        js_sys::Reflect::set(&obj, &"value".into(), value).unwrap();
+        JsValue::from(obj)
+    };
+    let stringify = |value: &JsValue| String::from(js_sys::JSON::stringify(value).unwrap());
+
+    // Plain values are rebuilt by Serde's buffering: equivalent, but not the same object.
+    let plain = js_sys::JSON::parse(r#"{"a":[1,"x",null],"b":{"c":true}}"#).unwrap();
+    let Untagged::Value { value } = from_value(input(&plain)).unwrap();
+    assert_ne!(value.0, plain);
+    assert_eq!(stringify(&value.0), stringify(&plain));
+    let Flattened { id, inner } = from_value(input(&plain)).unwrap();
+    assert_eq!(id, 1);
+    assert_ne!(inner.value.0, plain);
+    assert_eq!(stringify(&inner.value.0), stringify(&plain));
+
+    // Class instances lose their class, so the checked adapters reject them.
+    let date = JsValue::from(js_sys::Date::new(&0.into()));
+    let Untagged::Value { value } = from_value(input(&date)).unwrap();
+    assert!(!value.0.is_instance_of::<js_sys::Date>());
+    let Flattened { inner, .. } = from_value(input(&date)).unwrap();
+    assert!(!inner.value.0.is_instance_of::<js_sys::Date>());
+    from_value::<Checked>(input(&date)).unwrap_err();
+
+    // Functions have no structure to rebuild.
+    let function = JsValue::from(js_sys::Function::new_no_args(""));
+    assert!(from_value::<Untagged>(input(&function)).is_err());
+    assert!(from_value::<Flattened>(input(&function)).is_err());
+
+    // Outside of buffered types the same values are passed through untouched.
+    let Inner { value } = from_value(input(&date)).unwrap();
+    assert_eq!(value.0, date);
+}
+
+#[wasm_bindgen_test]
+fn string_interning() {
+    use serde_wasm_bindgen::{clear_string_cache, string_cache_stats, StringCacheStats};
+
+    clear_string_cache();
+    let serializer = Serializer::new().intern_strings(4);
+    let map: BTreeMap<String, String> = btreemap! {
+        "a".to_owned() => "same".to_owned(),
+        "b".to_owned() => "same".to_owned(),
+    };
+
+    let first = map.serialize(&serializer).unwrap();
+    assert_eq!(
+        string_cache_stats(),
+        StringCacheStats {
+            hits: 1,
+            misses: 3,
+            evictions: 0,
+            len: 3,
+        }
+    );
+
+    // Repeated serialization only hits the cache and produces equal values.
+    let second = map.serialize(&serializer).unwrap();
+    assert_eq!(string_cache_stats().hits, 5);
+    assert_eq!(string_cache_stats().misses, 3);
+    let get = |map: &JsValue, key: &str| map.unchecked_ref::<js_sys::Map>().get(&key.into());
+    assert_eq!(get(&first, "a"), get(&second, "b"));
+
+    // The cache stays within its capacity.
+    for i in 0..10 {
+        i.to_string().serialize(&serializer).unwrap();
+    }
+    let stats = string_cache_stats();
+    assert!(stats.len <= 4, "{:?}", stats);
+    assert!(stats.evictions > 0);
+
+    // Serializers without the option don't use the cache.
+    "uncached".serialize(&Serializer::new()).unwrap();
+    assert_eq!(string_cache_stats(), stats);
+
+    // Long strings bypass the cache.
+    let long = "x".repeat(65);
+    long.serialize(&serializer).unwrap();
+    long.serialize(&serializer).unwrap();
+    assert_eq!(string_cache_stats(), stats);
+    "x".repeat(64).serialize(&serializer).unwrap();
+    assert_eq!(string_cache_stats().misses, stats.misses + 1);
+
+    clear_string_cache();
+    assert_eq!(string_cache_stats(), StringCacheStats::default());
+}
+
+#[cfg(feature = "batch")]
+#[wasm_bindgen_test]
+fn batched_serialization() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct Test {
+        name: String,
+        flag: Option<bool>,
+        big: u64,
+        #[serde(with = "serde_bytes")]
+        bytes: Vec<u8>,
+        shapes: Vec<Shape>,
+        map: BTreeMap<String, (char, i32)>,
+        unit: (),
+    }
+
+    let value = Test {
+        name: "ünïcödé".to_owned(),
+        flag: None,
+        big: u64::MAX,
+        bytes: vec![1, 2, 3],
+        shapes: vec![
+            Shape::Empty,
+            Shape::Circle(0.5),
+            Shape::Rect { w: 1, h: 2 },
+            Shape::Rect { w: 3, h: 4 },
+        ],
+        map: btreemap! { "a".to_owned() => ('x', -1), "b".to_owned() => ('y', 2) },
+        unit: (),
+    };
+
+    let adjacent = EnumRepresentation::Adjacent {
+        tag: "type",
+        content: "value",
+    };
+    for (serializer, config, json) in [
+        (
+            Serializer::new().number_policy(NumberPolicy::NumberOrBigInt),
+            DeserializerConfig::new().number_policy(NumberPolicy::NumberOrBigInt),
+            false,
+        ),
+        (
+            Serializer::json_compatible().number_policy(NumberPolicy::String),
+            DeserializerConfig::new().number_policy(NumberPolicy::String),
+            true,
+        ),
+        (
+            Serializer::json_compatible()
+                .number_policy(NumberPolicy::String)
+                .enum_representation(adjacent),
+            DeserializerConfig::new()
+                .number_policy(NumberPolicy::String)
+                .enum_representation(adjacent),
+            true,
+        ),
+    ] {
+        let batched = serializer.serialize_batched(&value).unwrap();
+        let direct = value.serialize(&serializer).unwrap();
+        assert_eq!(
+            from_value_with::<Test>(batched.clone(), &config).unwrap(),
+            value
+        );
+        if json {
+            assert_eq!(
+                js_sys::JSON::stringify(&batched).unwrap(),
+                js_sys::JSON::stringify(&direct).unwrap()
+            );
+        }
+    }
+
+    // Errors are reported at the same paths as with the direct mode.
+    let err = Serializer::new().serialize_batched(&value).unwrap_err();
+    assert_eq!(err.path(), [PathSegment::Field("big".to_owned())]);
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+
+    let err = Serializer::new()
+        .enum_representation(EnumRepresentation::Internal { tag: "type" })
+        .serialize_batched(&Shape::Circle(1.0))
+        .unwrap_err();
+    assert!(err.to_string().contains("non-object value"));
+}
+
+#[cfg(feature = "batch")]
+#[wasm_bindgen_test]
+fn batched_deserialization() {
+    use serde_wasm_bindgen::from_value_batched;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+    struct Test {
+        name: String,
+        flag: Option<bool>,
+        big: u64,
+        #[serde(with = "serde_bytes")]
+        bytes: Vec<u8>,
+        shapes: Vec<Shape>,
+        map: BTreeMap<i32, (char, i32)>,
+        set: Vec<String>,
+        #[serde(with = "serde_wasm_bindgen::typed_array")]
+        floats: Vec<f32>,
+        #[serde(with = "serde_wasm_bindgen::preserve")]
+        date: js_sys::Date,
+        unit: (),
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(deny_unknown_fields)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    let value = Test {
+        name: "ünïcödé".to_owned(),
+        flag: None,
+        big: u64::MAX,
+        bytes: vec![1, 2, 3],
+        shapes: vec![Shape::Empty, Shape::Circle(0.5), Shape::Rect { w: 1, h: 2 }],
+        map: btreemap! { 1 => ('x', -1), 2 => ('y', 2) },
+        set: vec!["a".to_owned(), "b".to_owned()],
+        floats: vec![1.5, 2.5],
+        date: js_sys::Date::new(&0.into()),
+        unit: (),
+    };
+    let config = DeserializerConfig::new().number_policy(NumberPolicy::NumberOrBigInt);
+    let js = Serializer::new()
+        .number_policy(NumberPolicy::NumberOrBigInt)
+        .serialize_batched(&value)
+        .unwrap();
+    // `Set`s are accepted for sequences.
+    let set = js_sys::Set::new(&Array::of2(&"a".into(), &"b".into()));
+    js_sys::Reflect::set(&js, &"set".into(), &set).unwrap();
+
+    let output: Test = from_value_batched(js.clone(), &config).unwrap();
+    assert_eq!(output.date, value.date);
+    assert_eq!(
+        output,
+        from_value_with::<Test>(js.clone(), &config).unwrap()
+    );
+    assert_eq!(
+        Test {
+            date: value.date.clone(),
+            ..output
+        },
+        value
+    );
+
+    // Errors are reported at the same paths and shared or cyclic objects are supported.
+    let obj = js_sys::JSON::parse(r#"{"a": [{"w": 1, "h": "2"}]}"#).unwrap();
+    js_sys::Reflect::set(&obj, &"self".into(), &obj).unwrap();
+    let err = from_value_batched::<HashMap<String, Vec<Shape>>>(obj.clone(), &config);
+    let expected = from_value::<HashMap<String, Vec<Shape>>>(obj).unwrap_err();
+    let err = err.unwrap_err();
+    assert_eq!(err.to_string(), expected.to_string());
+    assert_eq!(err.path(), expected.path());
+
+    // Fields are matched regardless of the order of properties,
+    // and unknown properties are handled the same way.
+    let obj = js_sys::JSON::parse(r#"{"y": 2, "z": 3, "x": 1}"#).unwrap();
+    let point = from_value_batched::<Point>(obj.clone(), &config).unwrap();
+    assert_eq!(point, Point { x: 1, y: 2 });
+    let strict = DeserializerConfig::strict();
+    let err = from_value_batched::<Point>(obj.clone(), &strict).unwrap_err();
+    let expected = from_value_with::<Point>(obj, &strict).unwrap_err();
+    assert_eq!(err.to_string(), expected.to_string());
+}